@@ -1,5 +1,6 @@
-use crate::redis;
+use crate::{events, redis};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs::File;
 use thiserror::Error;
 
@@ -11,6 +12,12 @@ pub struct Config {
 
     #[serde(default)]
     pub targets: Vec<redis::connection::Config>,
+
+    #[serde(default)]
+    pub events: Option<events::Config>,
+
+    #[serde(default)]
+    pub baselines: HashMap<String, redis::drift::Baseline>,
 }
 
 fn default_prometheus_port() -> u32 {
@@ -24,9 +31,38 @@ pub enum Error {
 
     #[error("Unable to deserialize config file: {0}")]
     Parse(serde_yml::Error),
+
+    #[error("Unknown baseline group: {0}")]
+    UnknownBaseline(String),
+
+    #[error("Invalid configuration of target {0}: {1}")]
+    InvalidTarget(String, &'static str),
 }
 
 pub fn load(filepath: &str) -> Result<Config, Error> {
     let file = File::open(filepath).map_err(Error::IO)?;
-    serde_yml::from_reader(file).map_err(Error::Parse)
+    let mut config: Config = serde_yml::from_reader(file).map_err(Error::Parse)?;
+
+    // Targets extend the baseline of their group with their own parameters
+    for target in &mut config.targets {
+        if let Some(group) = &target.baseline_group {
+            let baseline = config
+                .baselines
+                .get(group)
+                .ok_or_else(|| Error::UnknownBaseline(group.clone()))?;
+            target.baseline = baseline.merge(&target.baseline);
+        }
+
+        if target
+            .malloc_stats
+            .as_ref()
+            .is_some_and(|malloc_stats| malloc_stats.interval_secs == 0)
+        {
+            return Err(Error::InvalidTarget(
+                target.target_name().to_string(),
+                "malloc_stats interval_secs must be greater than 0",
+            ));
+        }
+    }
+    Ok(config)
 }
@@ -0,0 +1,26 @@
+pub mod redact;
+pub mod sink;
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub file: Option<String>,
+    pub syslog: Option<String>,
+    pub max_arg_length: usize,
+    pub default_rules: bool,
+    pub redact: Vec<redact::Rule>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            file: None,
+            syslog: None,
+            max_arg_length: 128,
+            default_rules: true,
+            redact: vec![],
+        }
+    }
+}
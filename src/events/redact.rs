@@ -0,0 +1,122 @@
+use serde::Deserialize;
+
+const REDACTED: &str = "(redacted)";
+
+// Keeps the first `keep_args` arguments of a command and redacts the others.
+// The command `*` applies to every command without a rule of its own.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub command: String,
+    #[serde(default)]
+    pub keep_args: usize,
+}
+
+impl Rule {
+    fn new(command: &str, keep_args: usize) -> Self {
+        Self {
+            command: command.to_string(),
+            keep_args,
+        }
+    }
+}
+
+// Commands whose arguments may carry credentials.
+pub fn default_rules() -> Vec<Rule> {
+    vec![
+        Rule::new("auth", 0),
+        Rule::new("hello", 1),
+        Rule::new("acl", 2),
+        Rule::new("config", 2),
+        Rule::new("migrate", 5),
+    ]
+}
+
+// Rules of `overrides` replace the default rule of the same command, and the
+// defaults are only dropped with `default_rules: false`.
+pub fn merge_rules(defaults: bool, overrides: &[Rule]) -> Vec<Rule> {
+    let mut rules: Vec<Rule> = match defaults {
+        true => default_rules(),
+        false => vec![],
+    };
+    rules.retain(|rule| {
+        !overrides
+            .iter()
+            .any(|o| o.command.eq_ignore_ascii_case(&rule.command))
+    });
+    rules.extend(overrides.iter().cloned());
+    rules
+}
+
+pub fn redact(rules: &[Rule], command: &str, args: Vec<String>, max_length: usize) -> Vec<String> {
+    let rule = rules
+        .iter()
+        .find(|rule| rule.command.eq_ignore_ascii_case(command))
+        .or_else(|| rules.iter().find(|rule| rule.command == "*"));
+
+    args.into_iter()
+        .enumerate()
+        .map(|(i, arg)| match rule {
+            Some(rule) if i >= rule.keep_args => REDACTED.to_string(),
+            _ => truncate(arg, max_length),
+        })
+        .collect()
+}
+
+// Shortens an argument the way Redis does in its own slow log.
+fn truncate(arg: String, max_length: usize) -> String {
+    match arg.char_indices().nth(max_length) {
+        Some((end, _)) => format!("{}... ({} more bytes)", &arg[..end], arg.len() - end),
+        None => arg,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn arguments_are_redacted_and_truncated() {
+        let rules = default_rules();
+        assert_eq!(
+            redact(
+                &rules,
+                "CONFIG",
+                args(&["SET", "masterauth", "secret"]),
+                128
+            ),
+            args(&["SET", "masterauth", "(redacted)"])
+        );
+        assert_eq!(
+            redact(&rules, "auth", args(&["user", "secret"]), 128),
+            args(&["(redacted)", "(redacted)"])
+        );
+        assert_eq!(
+            redact(&rules, "set", args(&["key", "abcdef"]), 4),
+            args(&["key", "abcd... (2 more bytes)"])
+        );
+
+        let rules = merge_rules(true, &[Rule::new("*", 1), Rule::new("CONFIG", 3)]);
+        assert_eq!(
+            redact(&rules, "set", args(&["key", "value"]), 128),
+            args(&["key", "(redacted)"])
+        );
+        assert_eq!(
+            redact(&rules, "auth", args(&["secret"]), 128),
+            args(&["(redacted)"])
+        );
+        assert_eq!(
+            redact(&rules, "config", args(&["SET", "maxmemory", "1gb"]), 128),
+            args(&["SET", "maxmemory", "1gb"])
+        );
+
+        let rules = merge_rules(false, &[]);
+        assert_eq!(
+            redact(&rules, "auth", args(&["secret"]), 128),
+            args(&["secret"])
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use crate::events::{redact, Config};
+use log::{error, info};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use tokio::net::UnixDatagram;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+// Facility local0, severity informational.
+const SYSLOG_PRIORITY: u8 = 134;
+const SYSLOG_TAG: &str = "redis-exporter";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Exactly one of file or syslog must be set for events")]
+    Output,
+
+    #[error("Unable to open events output {0}: {1}")]
+    Open(String, std::io::Error),
+
+    #[error("Unable to serialize event: {0}")]
+    Serialize(serde_json::Error),
+
+    #[error("Unable to write event: {0}")]
+    Write(std::io::Error),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowlogEvent {
+    pub target: String,
+    pub target_name: String,
+    pub id: u64,
+    pub timestamp: u64,
+    pub duration_usec: u64,
+    pub command: String,
+    pub args: Vec<String>,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+enum Output {
+    File(File),
+    Syslog { socket: UnixDatagram, path: String },
+}
+
+pub struct Sink {
+    output: Output,
+    max_arg_length: usize,
+    redact: Vec<redact::Rule>,
+}
+
+impl Sink {
+    pub async fn open(config: Config) -> Result<Self, Error> {
+        let output = match (config.file, config.syslog) {
+            (Some(path), None) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .await
+                    .map_err(|e| Error::Open(path.clone(), e))?;
+                Output::File(file)
+            }
+            (None, Some(path)) => {
+                let socket = UnixDatagram::unbound().map_err(|e| Error::Open(path.clone(), e))?;
+                Output::Syslog { socket, path }
+            }
+            _ => return Err(Error::Output),
+        };
+
+        Ok(Self {
+            output,
+            max_arg_length: config.max_arg_length,
+            redact: redact::merge_rules(config.default_rules, &config.redact),
+        })
+    }
+
+    pub async fn run(
+        mut self,
+        mut events: mpsc::Receiver<SlowlogEvent>,
+        cancellation_token: CancellationToken,
+    ) {
+        loop {
+            tokio::select! {
+                Some(event) = events.recv() => {
+                    if let Err(e) = self.write(event).await {
+                        error!("Error forwarding event: {}", e);
+                    }
+                }
+                _ = cancellation_token.cancelled() => {
+                    info!("Shutting down event sink");
+                    break;
+                }
+            }
+        }
+
+        // Events already queued are written before returning
+        events.close();
+        while let Some(event) = events.recv().await {
+            if let Err(e) = self.write(event).await {
+                error!("Error forwarding event: {}", e);
+            }
+        }
+    }
+
+    async fn write(&mut self, mut event: SlowlogEvent) -> Result<(), Error> {
+        event.args = redact::redact(
+            &self.redact,
+            &event.command,
+            event.args,
+            self.max_arg_length,
+        );
+        let line = serde_json::to_string(&event).map_err(Error::Serialize)?;
+
+        match &mut self.output {
+            Output::File(file) => {
+                file.write_all(format!("{}\n", line).as_bytes())
+                    .await
+                    .map_err(Error::Write)?;
+                file.flush().await.map_err(Error::Write)
+            }
+            Output::Syslog { socket, path } => {
+                let message = format!("<{}>{}: {}", SYSLOG_PRIORITY, SYSLOG_TAG, line);
+                socket
+                    .send_to(message.as_bytes(), path.as_str())
+                    .await
+                    .map(|_| ())
+                    .map_err(Error::Write)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn queued_events_are_written_to_the_file() {
+        let path = std::env::temp_dir().join(format!("slowlog-{}.jsonl", std::process::id()));
+        let config = Config {
+            file: Some(path.to_string_lossy().to_string()),
+            ..Config::default()
+        };
+        let sink = Sink::open(config).await.unwrap();
+
+        let (sender, receiver) = mpsc::channel(8);
+        let cancellation_token = CancellationToken::new();
+        let event = |id: u64, command: &str, args: &[&str]| SlowlogEvent {
+            target: "redis://127.0.0.1:6379".to_string(),
+            target_name: "cache".to_string(),
+            id,
+            timestamp: 1_700_000_000,
+            duration_usec: 12_000,
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            client_addr: "127.0.0.1:51234".to_string(),
+            client_name: "".to_string(),
+        };
+        sender.send(event(1, "auth", &["secret"])).await.unwrap();
+        sender.send(event(2, "get", &["key"])).await.unwrap();
+        // Cancelled with events still queued
+        cancellation_token.cancel();
+        sink.run(receiver, cancellation_token).await;
+
+        let lines = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let events: Vec<serde_json::Value> = lines
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["id"], 1);
+        assert_eq!(events[0]["target_name"], "cache");
+        assert_eq!(events[0]["args"], serde_json::json!(["(redacted)"]));
+        assert_eq!(events[1]["command"], "get");
+        assert_eq!(events[1]["args"], serde_json::json!(["key"]));
+    }
+}
@@ -1,13 +1,22 @@
+use crate::redis::collector::{Collect, Target};
 use clap::Parser;
 use futures_util::future;
 use log::{debug, error, info};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::signal;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
 use tokio_util::sync::CancellationToken;
 
 mod config;
-mod redis;
+mod events;
 mod prometheus;
+mod redis;
+
+// Slow log events waiting to be written, beyond which new events are dropped.
+const EVENTS_CHANNEL_CAPACITY: usize = 1024;
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
@@ -17,11 +26,19 @@ struct CliArgs {
 
     #[arg(long, default_value_t = log::LevelFilter::Info)]
     log_level: log::LevelFilter,
+
+    #[arg(long)]
+    list_metrics: bool,
 }
 
 #[tokio::main]
 async fn main() {
     let cli_args = CliArgs::parse();
+    if cli_args.list_metrics {
+        redis::metrics::print_metrics();
+        return;
+    }
+
     env_logger::builder()
         .filter_level(cli_args.log_level)
         .init();
@@ -37,36 +54,141 @@ async fn run(args: CliArgs) -> Result<(), anyhow::Error> {
 
     shutdown(cancellation_token.clone());
 
-    let mut futures = Vec::with_capacity(config.targets.len());
-    for target in config.targets {
-        let conn = redis::connection::get_connection(target.clone()).await?;
-        let target_url = target.url.clone();
-        let target_name = target.name.unwrap_or(target_url.clone());
-        let collector = redis::metrics::Collector::new(conn, &target.url, &target_name)?;
-
-        let cancel = cancellation_token.clone();
-        futures.push(tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(config.collect_interval));
-            loop {
-                tokio::select! {
-                    _ = interval.tick() => {
-                        debug!("Collecting metrics for {} ({})", &target_name, &target_url);
-                        if let Err(e) = collector.collect().await {
-                            error!(
-                                "Error collecting metrics for target {}: {}",
-                                &target_name, e
-                            )
-                        }
-                }
-                _ = cancel.cancelled() => {
-                        info!("Shutting down collector for target {}", &target_name);
-                        break;
-                    }
-                }
-            }
-        }));
+    let registry = ::prometheus::default_registry();
+    let metrics = Arc::new(redis::metrics::Metrics::new(registry)?);
+    let latency_histogram_metrics = Arc::new(redis::latency_histogram::Metrics::new(registry)?);
+    let latency_monitor_metrics = Arc::new(redis::latency_monitor::Metrics::new(registry)?);
+    let memory_stats_metrics = Arc::new(redis::memory_stats::Metrics::new(registry)?);
+    let malloc_stats_metrics = Arc::new(redis::malloc_stats::Metrics::new(registry)?);
+    let clients_metrics = Arc::new(redis::clients::Metrics::new(registry)?);
+    let config_get_metrics = Arc::new(redis::config_get::Metrics::new(registry)?);
+    let drift_metrics = Arc::new(redis::drift::Metrics::new(registry)?);
+    let security_metrics = Arc::new(redis::security::Metrics::new(registry)?);
+    let acl_metrics = Arc::new(redis::acl::Metrics::new(registry)?);
+    let keys_metrics = Arc::new(redis::keys::Metrics::new(registry)?);
+    let slowlog_metrics = Arc::new(redis::slowlog::Metrics::new(registry)?);
+
+    let mut events = None;
+    let mut sink_handler = None;
+    if let Some(events_config) = config.events {
+        let sink = events::sink::Sink::open(events_config).await?;
+        let (sender, receiver) = mpsc::channel(EVENTS_CHANNEL_CAPACITY);
+        events = Some(sender);
+        sink_handler = Some(tokio::spawn(sink.run(receiver, cancellation_token.clone())));
     }
 
+    let collect_interval = Duration::from_secs(config.collect_interval);
+    let cancel = &cancellation_token;
+    let mut futures = Vec::with_capacity(config.targets.len());
+    for target_config in config.targets {
+        let target = Target::new(&target_config)?;
+
+        futures.push(spawn_collector(
+            redis::metrics::Collector::new(target.clone(), metrics.clone(), &target_config),
+            collect_interval,
+            cancel.clone(),
+        ));
+        if target_config.latency_histogram {
+            futures.push(spawn_collector(
+                redis::latency_histogram::Collector::new(
+                    target.clone(),
+                    latency_histogram_metrics.clone(),
+                ),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if target_config.latency_monitor {
+            futures.push(spawn_collector(
+                redis::latency_monitor::Collector::new(
+                    target.clone(),
+                    latency_monitor_metrics.clone(),
+                ),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if target_config.memory_stats {
+            futures.push(spawn_collector(
+                redis::memory_stats::Collector::new(target.clone(), memory_stats_metrics.clone()),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if let Some(malloc_stats) = &target_config.malloc_stats {
+            futures.push(spawn_collector(
+                redis::malloc_stats::Collector::new(target.clone(), malloc_stats_metrics.clone()),
+                Duration::from_secs(malloc_stats.interval_secs),
+                cancel.clone(),
+            ));
+        }
+        if let Some(client_list) = &target_config.client_list {
+            futures.push(spawn_collector(
+                redis::clients::Collector::new(
+                    target.clone(),
+                    clients_metrics.clone(),
+                    client_list,
+                ),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if let Some(config_get) = &target_config.config_get {
+            futures.push(spawn_collector(
+                redis::config_get::Collector::new(
+                    target.clone(),
+                    config_get_metrics.clone(),
+                    config_get,
+                ),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if !target_config.baseline.is_empty() {
+            futures.push(spawn_collector(
+                redis::drift::Collector::new(
+                    target.clone(),
+                    drift_metrics.clone(),
+                    &target_config.baseline,
+                ),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if target_config.security_checks {
+            futures.push(spawn_collector(
+                redis::security::Collector::new(target.clone(), security_metrics.clone()),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if target_config.acl {
+            futures.push(spawn_collector(
+                redis::acl::Collector::new(target.clone(), acl_metrics.clone()),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if !target_config.check_keys.is_empty() {
+            futures.push(spawn_collector(
+                redis::keys::Collector::new(target.clone(), keys_metrics.clone(), &target_config),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+        if let Some(slowlog) = &target_config.slowlog {
+            futures.push(spawn_collector(
+                redis::slowlog::Collector::new(
+                    target.clone(),
+                    slowlog_metrics.clone(),
+                    slowlog,
+                    events.clone(),
+                ),
+                collect_interval,
+                cancel.clone(),
+            ));
+        }
+    }
 
     let prom_handler = tokio::spawn(async move {
         info!("Starting prometheus webserver");
@@ -75,10 +197,57 @@ async fn run(args: CliArgs) -> Result<(), anyhow::Error> {
 
     future::join_all(futures).await;
     let _ = prom_handler.await?;
+    if let Some(sink_handler) = sink_handler {
+        sink_handler.await?;
+    }
 
     Ok(())
 }
 
+fn spawn_collector<C: Collect>(
+    collector: C,
+    period: Duration,
+    cancel: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let target = collector.target();
+        let mut interval = tokio::time::interval(period);
+        // A collection slower than the period is not followed by a burst
+        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = cancel.cancelled() => break,
+            }
+
+            debug!(
+                "Collecting {} metrics for {} ({})",
+                C::NAME,
+                &target.name,
+                &target.url
+            );
+            // A collection in progress, e.g. connecting to an unreachable
+            // target, does not delay the shutdown
+            tokio::select! {
+                result = collector.collect() => {
+                    if let Err(e) = result {
+                        error!(
+                            "Error collecting {} metrics for target {}: {}",
+                            C::NAME, &target.name, e
+                        )
+                    }
+                }
+                _ = cancel.cancelled() => break,
+            }
+        }
+        info!(
+            "Shutting down {} collector for target {}",
+            C::NAME,
+            &target.name
+        );
+    })
+}
+
 fn shutdown(cancellation_token: CancellationToken) {
     tokio::spawn(async move {
         let mut terminate = signal::unix::signal(signal::unix::SignalKind::terminate()).unwrap();
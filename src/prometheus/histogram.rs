@@ -0,0 +1,127 @@
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::{Bucket, Histogram, LabelPair, Metric, MetricFamily, MetricType};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// A histogram whose buckets are computed elsewhere (by Redis, or from a
+// snapshot of the current clients) rather than from individual observations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistogramSnapshot {
+    pub buckets: Vec<(f64, u64)>,
+    pub count: u64,
+    pub sum: f64,
+}
+
+impl HistogramSnapshot {
+    // Builds cumulative buckets over fixed upper bounds from a list of samples.
+    pub fn from_samples(bounds: &[f64], samples: &[f64]) -> Self {
+        let buckets = bounds
+            .iter()
+            .map(|bound| {
+                (
+                    *bound,
+                    samples.iter().filter(|s| *s <= bound).count() as u64,
+                )
+            })
+            .collect();
+        Self {
+            buckets,
+            count: samples.len() as u64,
+            sum: samples.iter().sum(),
+        }
+    }
+}
+
+type Series = Vec<(Vec<String>, HistogramSnapshot)>;
+
+#[derive(Clone)]
+pub struct SnapshotHistogramVec {
+    desc: Arc<Desc>,
+    label_names: Arc<Vec<String>>,
+    series: Arc<Mutex<HashMap<String, Series>>>,
+}
+
+impl SnapshotHistogramVec {
+    pub fn new(
+        namespace: &str,
+        name: &str,
+        help: &str,
+        label_names: &[&str],
+    ) -> Result<Self, prometheus::Error> {
+        let label_names: Vec<String> = label_names.iter().map(|l| l.to_string()).collect();
+        let desc = Desc::new(
+            format!("{}_{}", namespace, name),
+            help.to_string(),
+            label_names.clone(),
+            HashMap::new(),
+        )?;
+        Ok(Self {
+            desc: Arc::new(desc),
+            label_names: Arc::new(label_names),
+            series: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    // Replaces every series of the target with the given label values and snapshots.
+    pub fn set_target(&self, target: &str, series: Series) {
+        self.series
+            .lock()
+            .unwrap()
+            .insert(target.to_string(), series);
+    }
+}
+
+impl Collector for SnapshotHistogramVec {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let mut metrics = Vec::new();
+        for (label_values, snapshot) in self.series.lock().unwrap().values().flatten() {
+            let mut labels: Vec<LabelPair> = self
+                .label_names
+                .iter()
+                .zip(label_values)
+                .map(|(name, value)| {
+                    let mut label = LabelPair::default();
+                    label.set_name(name.clone());
+                    label.set_value(value.clone());
+                    label
+                })
+                .collect();
+            labels.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+
+            let buckets: Vec<Bucket> = snapshot
+                .buckets
+                .iter()
+                .map(|(upper_bound, count)| {
+                    let mut bucket = Bucket::default();
+                    bucket.set_upper_bound(*upper_bound);
+                    bucket.set_cumulative_count(*count);
+                    bucket
+                })
+                .collect();
+            let mut histogram = Histogram::default();
+            histogram.set_sample_count(snapshot.count);
+            histogram.set_sample_sum(snapshot.sum);
+            histogram.set_bucket(buckets.into());
+
+            let mut metric = Metric::default();
+            metric.set_label(labels.into());
+            metric.set_histogram(histogram);
+            metrics.push(metric);
+        }
+
+        if metrics.is_empty() {
+            return vec![];
+        }
+
+        let mut family = MetricFamily::default();
+        family.set_name(self.desc.fq_name.clone());
+        family.set_help(self.desc.help.clone());
+        family.set_field_type(MetricType::HISTOGRAM);
+        family.set_metric(metrics.into());
+        vec![family]
+    }
+}
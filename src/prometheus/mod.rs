@@ -1 +1,3 @@
+pub mod histogram;
+pub mod series;
 pub mod server;
@@ -0,0 +1,66 @@
+use prometheus::core::{MetricVec, MetricVecBuilder};
+use prometheus::{register_gauge_vec_with_registry, GaugeVec, Opts, Registry};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+// Remembers which label values each target exported on its previous collection,
+// so series that disappear from Redis (a disconnected replica, an emptied
+// database, ...) are removed instead of staying frozen at their last value.
+#[derive(Default)]
+pub struct SeriesTracker {
+    seen: Mutex<HashMap<String, HashSet<Vec<String>>>>,
+}
+
+impl SeriesTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn retain<P: MetricVecBuilder>(
+        &self,
+        target: &str,
+        current: HashSet<Vec<String>>,
+        vecs: &[&MetricVec<P>],
+    ) {
+        let mut seen = self.seen.lock().unwrap();
+        if let Some(previous) = seen.get(target) {
+            for labels in previous.difference(&current) {
+                let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+                for vec in vecs {
+                    let _ = vec.remove_label_values(&labels);
+                }
+            }
+        }
+        seen.insert(target.to_string(), current);
+    }
+}
+
+// A gauge family whose series for a target are replaced as a whole on every
+// collection, for replies that list a variable set of items.
+pub struct SnapshotGaugeVec {
+    vec: GaugeVec,
+    series: SeriesTracker,
+}
+
+impl SnapshotGaugeVec {
+    pub fn new(
+        opts: Opts,
+        label_names: &[&str],
+        registry: &Registry,
+    ) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            vec: register_gauge_vec_with_registry!(opts, label_names, registry)?,
+            series: SeriesTracker::new(),
+        })
+    }
+
+    pub fn set_target(&self, target: &str, values: Vec<(Vec<String>, f64)>) {
+        let mut current = HashSet::with_capacity(values.len());
+        for (labels, value) in values {
+            let label_values: Vec<&str> = labels.iter().map(String::as_str).collect();
+            self.vec.with_label_values(&label_values).set(value);
+            current.insert(labels);
+        }
+        self.series.retain(target, current, &[&self.vec]);
+    }
+}
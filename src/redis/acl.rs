@@ -0,0 +1,333 @@
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::debug;
+use prometheus::{
+    opts, register_counter_vec_with_registry, register_gauge_vec_with_registry, CounterVec,
+    GaugeVec, Registry,
+};
+use redis::{RedisResult, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// Default value of acllog-max-len, ACL LOG only returns 10 entries otherwise.
+const ACL_LOG_ENTRIES: u64 = 128;
+
+// Label value of the denials of users that do not exist, which failed AUTH
+// attempts may carry in any number.
+const OTHER: &str = "other";
+
+pub struct Collector {
+    target: Target,
+    // Count of each log entry seen on the previous collection, None until the
+    // first collection has recorded a baseline.
+    seen: Mutex<Option<HashMap<EntryKey, u64>>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>) -> Self {
+        Self {
+            target,
+            seen: Mutex::new(None),
+            metrics,
+        }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "acl";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let (log, users): (Value, Vec<String>) = redis::pipe()
+            .cmd("ACL")
+            .arg("LOG")
+            .arg(ACL_LOG_ENTRIES)
+            .cmd("ACL")
+            .arg("LIST")
+            .query_async(&mut self.target.conn.get().await?)
+            .await?;
+
+        debug!("Receive data from ACL LOG and ACL LIST commands");
+        let entries = parse_log(&log);
+        let mut seen = self.seen.lock().unwrap();
+        let denials = match seen.as_mut() {
+            Some(seen) => new_denials(seen, &entries),
+            None => {
+                let mut baseline = HashMap::new();
+                new_denials(&mut baseline, &entries);
+                *seen = Some(baseline);
+                vec![]
+            }
+        };
+
+        let users: Vec<(String, bool)> = users.iter().filter_map(|u| parse_user(u)).collect();
+        self.metrics
+            .update(&self.target.url, &self.target.name, &denials, &users);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    denials_total: CounterVec,
+    users: GaugeVec,
+    user_enabled: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            denials_total: register_counter_vec_with_registry!(
+                opts!(
+                    "acl_denials_total",
+                    "Total number of ACL denials logged since the exporter started"
+                )
+                .namespace("redis"),
+                &["reason", "context", "username", "target", "target_name"],
+                registry
+            )?,
+            users: register_gauge_vec_with_registry!(
+                opts!("acl_users", "Number of ACL users").namespace("redis"),
+                &["target", "target_name"],
+                registry
+            )?,
+            user_enabled: SnapshotGaugeVec::new(
+                opts!(
+                    "acl_user_enabled",
+                    "Whether an ACL user is enabled (1) or disabled (0)"
+                )
+                .namespace("redis"),
+                &["username", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+
+    fn update(
+        &self,
+        target: &str,
+        target_name: &str,
+        denials: &[(&Entry, u64)],
+        users: &[(String, bool)],
+    ) {
+        for (entry, count) in denials {
+            let username = match users.iter().any(|(u, _)| *u == entry.key.username) {
+                true => &entry.key.username,
+                false => OTHER,
+            };
+            self.denials_total
+                .with_label_values(&[
+                    &entry.key.reason,
+                    &entry.key.context,
+                    username,
+                    target,
+                    target_name,
+                ])
+                .inc_by(*count as f64);
+        }
+
+        self.users
+            .with_label_values(&[target, target_name])
+            .set(users.len() as f64);
+        self.user_enabled.set_target(
+            target,
+            users
+                .iter()
+                .map(|(username, enabled)| {
+                    (
+                        vec![
+                            username.clone(),
+                            target.to_string(),
+                            target_name.to_string(),
+                        ],
+                        if *enabled { 1f64 } else { 0f64 },
+                    )
+                })
+                .collect(),
+        );
+    }
+}
+
+// Redis merges repeated denials into one entry and increments its count.
+// Entries are identified by `entry-id` since Redis 7.2, and by what they
+// were denied otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct EntryKey {
+    entry_id: Option<u64>,
+    reason: String,
+    context: String,
+    object: String,
+    username: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Entry {
+    key: EntryKey,
+    count: u64,
+}
+
+fn parse_log(reply: &Value) -> Vec<Entry> {
+    let Value::Array(entries) = reply else {
+        return vec![];
+    };
+
+    entries
+        .iter()
+        .map(|entry| {
+            let fields: HashMap<String, &Value> = reply::as_pairs(entry)
+                .into_iter()
+                .filter_map(|(key, value)| Some((reply::as_string(key)?, value)))
+                .collect();
+            let field = |name: &str| fields.get(name).and_then(|v| reply::as_string(v));
+            let number = |name: &str| fields.get(name).and_then(|v| reply::as_f64(v));
+            Entry {
+                key: EntryKey {
+                    entry_id: number("entry-id").map(|id| id as u64),
+                    reason: field("reason").unwrap_or_default(),
+                    context: field("context").unwrap_or_default(),
+                    object: field("object").unwrap_or_default(),
+                    username: field("username").unwrap_or_default(),
+                },
+                count: number("count").unwrap_or(0f64) as u64,
+            }
+        })
+        .collect()
+}
+
+// Returns the denials added to each entry since the previous collection and
+// forgets the entries that left the log. A count lower than the previous one
+// means the log was reset and the entry started over.
+fn new_denials<'a>(
+    seen: &mut HashMap<EntryKey, u64>,
+    entries: &'a [Entry],
+) -> Vec<(&'a Entry, u64)> {
+    let mut current = HashMap::with_capacity(entries.len());
+    let mut denials = Vec::new();
+    for entry in entries {
+        let previous = seen.get(&entry.key).copied().unwrap_or(0);
+        let count = if entry.count >= previous {
+            entry.count - previous
+        } else {
+            entry.count
+        };
+        if count > 0 {
+            denials.push((entry, count));
+        }
+        current.insert(entry.key.clone(), entry.count);
+    }
+    *seen = current;
+    denials
+}
+
+// `user default on nopass sanitize-payload ~* &* +@all`
+fn parse_user(line: &str) -> Option<(String, bool)> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next()? != "user" {
+        return None;
+    }
+    let username = tokens.next()?.to_string();
+    let enabled = tokens.any(|token| token == "on");
+    Some((username, enabled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(entry_id: i64, count: i64, username: &str) -> Value {
+        let bulk = |s: &str| Value::BulkString(s.as_bytes().to_vec());
+        Value::Array(vec![
+            bulk("count"),
+            Value::Int(count),
+            bulk("reason"),
+            bulk("auth"),
+            bulk("context"),
+            bulk("toplevel"),
+            bulk("object"),
+            bulk("AUTH"),
+            bulk("username"),
+            bulk(username),
+            bulk("entry-id"),
+            Value::Int(entry_id),
+        ])
+    }
+
+    #[test]
+    fn denials_are_counted_once() {
+        let counts = |denials: Vec<(&Entry, u64)>| {
+            denials
+                .into_iter()
+                .map(|(entry, count)| (entry.key.username.clone(), count))
+                .collect::<Vec<_>>()
+        };
+        let mut seen = HashMap::new();
+
+        let log = parse_log(&Value::Array(vec![entry(0, 3, "app")]));
+        assert_eq!(
+            counts(new_denials(&mut seen, &log)),
+            vec![("app".to_string(), 3)]
+        );
+
+        let log = parse_log(&Value::Array(vec![entry(1, 1, "cron"), entry(0, 5, "app")]));
+        assert_eq!(
+            counts(new_denials(&mut seen, &log)),
+            vec![("cron".to_string(), 1), ("app".to_string(), 2)]
+        );
+        assert!(new_denials(&mut seen, &log).is_empty());
+
+        // ACL LOG RESET
+        assert!(new_denials(&mut seen, &[]).is_empty());
+        let log = parse_log(&Value::Array(vec![entry(0, 1, "app")]));
+        assert_eq!(
+            counts(new_denials(&mut seen, &log)),
+            vec![("app".to_string(), 1)]
+        );
+
+        assert_eq!(
+            parse_user("user default off nopass ~* &* +@all"),
+            Some(("default".to_string(), false))
+        );
+    }
+
+    #[test]
+    fn unknown_users_are_folded() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let log = parse_log(&Value::Array(vec![
+            entry(2, 1, "admin"),
+            entry(1, 4, "root"),
+            entry(0, 2, "app"),
+        ]));
+        let denials: Vec<(&Entry, u64)> = log.iter().map(|entry| (entry, entry.count)).collect();
+        metrics.update("t", "t", &denials, &[("app".to_string(), true)]);
+
+        let mut usernames: Vec<(String, f64)> = registry
+            .gather()
+            .iter()
+            .find(|family| family.get_name() == "redis_acl_denials_total")
+            .unwrap()
+            .get_metric()
+            .iter()
+            .map(|metric| {
+                let username = metric
+                    .get_label()
+                    .iter()
+                    .find(|label| label.get_name() == "username")
+                    .unwrap()
+                    .get_value()
+                    .to_string();
+                (username, metric.get_counter().get_value())
+            })
+            .collect();
+        usernames.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            usernames,
+            vec![("app".to_string(), 2f64), (OTHER.to_string(), 5f64)]
+        );
+    }
+}
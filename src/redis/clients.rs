@@ -0,0 +1,390 @@
+use crate::prometheus::histogram::HistogramSnapshot;
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use log::debug;
+use prometheus::{opts, Registry};
+use redis::RedisResult;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+const DURATION_BUCKETS: [f64; 9] = [
+    1.0, 10.0, 60.0, 300.0, 900.0, 3600.0, 21600.0, 86400.0, 604800.0,
+];
+
+// Label value used for the groups that exceed `max_groups`.
+const OTHER: &str = "other";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub top_n: usize,
+    pub max_groups: usize,
+    pub group_by_subnet: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            top_n: 10,
+            max_groups: 50,
+            group_by_subnet: false,
+        }
+    }
+}
+
+pub struct Collector {
+    target: Target,
+    config: Config,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>, client_list: &Config) -> Self {
+        Self {
+            target,
+            config: client_list.clone(),
+            metrics,
+        }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "client list";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let client_list: String = redis::cmd("CLIENT")
+            .arg("LIST")
+            .query_async(&mut self.target.conn.get().await?)
+            .await?;
+
+        debug!("Receive data from CLIENT LIST command");
+        let clients: Vec<Client> = client_list.lines().filter_map(Client::parse).collect();
+        self.metrics
+            .update(&self.target.url, &self.target.name, &self.config, &clients);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    by_name: SnapshotGaugeVec,
+    by_lib: SnapshotGaugeVec,
+    by_user: SnapshotGaugeVec,
+    by_source: SnapshotGaugeVec,
+    by_flags: SnapshotGaugeVec,
+    idle: SnapshotGaugeVec,
+    age: SnapshotGaugeVec,
+    top_total_memory: SnapshotGaugeVec,
+    top_output_memory: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            by_name: SnapshotGaugeVec::new(
+                opts!(
+                    "clients_by_name",
+                    "Number of connected clients by client name"
+                )
+                .namespace("redis"),
+                &["client_name", "target", "target_name"],
+                registry,
+            )?,
+            by_lib: SnapshotGaugeVec::new(
+                opts!(
+                    "clients_by_lib",
+                    "Number of connected clients by client library and version"
+                )
+                .namespace("redis"),
+                &["lib_name", "lib_ver", "target", "target_name"],
+                registry,
+            )?,
+            by_user: SnapshotGaugeVec::new(
+                opts!("clients_by_user", "Number of connected clients by ACL user")
+                    .namespace("redis"),
+                &["user", "target", "target_name"],
+                registry,
+            )?,
+            by_source: SnapshotGaugeVec::new(
+                opts!(
+                    "clients_by_source",
+                    "Number of connected clients by source address or subnet"
+                )
+                .namespace("redis"),
+                &["source", "target", "target_name"],
+                registry,
+            )?,
+            by_flags: SnapshotGaugeVec::new(
+                opts!(
+                    "clients_by_flags",
+                    "Number of connected clients by client flags"
+                )
+                .namespace("redis"),
+                &["flags", "target", "target_name"],
+                registry,
+            )?,
+            // Gauges rather than histograms, as the bucket counts of a point
+            // in time distribution go down as well
+            idle: SnapshotGaugeVec::new(
+                opts!(
+                    "clients_idle_seconds_bucket_count",
+                    "Number of connected clients idle for at most le seconds"
+                )
+                .namespace("redis"),
+                &["le", "target", "target_name"],
+                registry,
+            )?,
+            age: SnapshotGaugeVec::new(
+                opts!(
+                    "clients_age_seconds_bucket_count",
+                    "Number of connected clients connected for at most le seconds"
+                )
+                .namespace("redis"),
+                &["le", "target", "target_name"],
+                registry,
+            )?,
+            top_total_memory: SnapshotGaugeVec::new(
+                opts!(
+                    "client_top_total_memory_bytes",
+                    "Total memory of the clients using the most memory"
+                )
+                .namespace("redis"),
+                &["id", "addr", "client_name", "target", "target_name"],
+                registry,
+            )?,
+            top_output_memory: SnapshotGaugeVec::new(
+                opts!(
+                    "client_top_output_memory_bytes",
+                    "Output buffer memory of the clients using the most output buffer memory"
+                )
+                .namespace("redis"),
+                &["id", "addr", "client_name", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+
+    fn update(&self, target: &str, target_name: &str, config: &Config, clients: &[Client]) {
+        let group = |key: &dyn Fn(&Client) -> Vec<String>| {
+            let mut counts: HashMap<Vec<String>, f64> = HashMap::new();
+            for client in clients {
+                *counts.entry(key(client)).or_default() += 1f64;
+            }
+            cap_groups(counts, config.max_groups)
+                .into_iter()
+                .map(|(mut labels, count)| {
+                    labels.extend([target.to_string(), target_name.to_string()]);
+                    (labels, count)
+                })
+                .collect()
+        };
+
+        self.by_name
+            .set_target(target, group(&|c| vec![c.name.to_string()]));
+        self.by_lib.set_target(
+            target,
+            group(&|c| vec![c.lib_name.to_string(), c.lib_ver.to_string()]),
+        );
+        self.by_user
+            .set_target(target, group(&|c| vec![c.user.to_string()]));
+        self.by_source.set_target(
+            target,
+            group(&|c| vec![source(c.addr, config.group_by_subnet)]),
+        );
+        self.by_flags
+            .set_target(target, group(&|c| vec![c.flags.to_string()]));
+
+        let buckets = |value: fn(&Client) -> f64| {
+            let samples: Vec<f64> = clients.iter().map(value).collect();
+            let snapshot = HistogramSnapshot::from_samples(&DURATION_BUCKETS, &samples);
+            snapshot
+                .buckets
+                .iter()
+                .map(|(bound, count)| (bound.to_string(), *count as f64))
+                .chain([("+Inf".to_string(), snapshot.count as f64)])
+                .map(|(le, count)| (vec![le, target.to_string(), target_name.to_string()], count))
+                .collect()
+        };
+        self.idle.set_target(target, buckets(|c| c.idle));
+        self.age.set_target(target, buckets(|c| c.age));
+
+        let top = |value: fn(&Client) -> f64| {
+            let mut sorted: Vec<&Client> = clients.iter().filter(|c| value(c) > 0f64).collect();
+            sorted.sort_by(|a, b| value(b).total_cmp(&value(a)));
+            sorted
+                .into_iter()
+                .take(config.top_n)
+                .map(|c| {
+                    let labels = [c.id, c.addr, c.name, target, target_name];
+                    (labels.map(String::from).to_vec(), value(c))
+                })
+                .collect()
+        };
+        self.top_total_memory
+            .set_target(target, top(|c| c.total_memory));
+        self.top_output_memory
+            .set_target(target, top(|c| c.output_memory));
+    }
+}
+
+// A line of CLIENT LIST, e.g. `id=3 addr=127.0.0.1:51932 ... name= age=4 idle=0 ...`.
+struct Client<'a> {
+    id: &'a str,
+    addr: &'a str,
+    name: &'a str,
+    user: &'a str,
+    flags: &'a str,
+    lib_name: &'a str,
+    lib_ver: &'a str,
+    age: f64,
+    idle: f64,
+    total_memory: f64,
+    output_memory: f64,
+}
+
+impl<'a> Client<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        let fields: HashMap<&str, &str> = line
+            .split(' ')
+            .filter_map(|field| field.split_once('='))
+            .collect();
+        let field = |name: &str| fields.get(name).copied().unwrap_or("");
+        let number = |name: &str| field(name).parse::<f64>().unwrap_or(0f64);
+
+        Some(Self {
+            id: fields.get("id")?,
+            addr: field("addr"),
+            name: field("name"),
+            user: field("user"),
+            flags: field("flags"),
+            lib_name: field("lib-name"),
+            lib_ver: field("lib-ver"),
+            age: number("age"),
+            idle: number("idle"),
+            total_memory: number("tot-mem"),
+            output_memory: number("omem"),
+        })
+    }
+}
+
+// Client IP address, or its /24 (IPv4) or /64 (IPv6) subnet.
+fn source(addr: &str, group_by_subnet: bool) -> String {
+    let ip = addr.rsplit_once(':').map(|(ip, _)| ip).unwrap_or(addr);
+    let ip = ip.trim_start_matches('[').trim_end_matches(']');
+    match (ip.parse::<IpAddr>(), group_by_subnet) {
+        (Ok(IpAddr::V4(ip)), true) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{}.{}.{}.0/24", a, b, c)
+        }
+        (Ok(IpAddr::V6(ip)), true) => {
+            let segments = ip.segments();
+            format!(
+                "{:x}:{:x}:{:x}:{:x}::/64",
+                segments[0], segments[1], segments[2], segments[3]
+            )
+        }
+        _ => ip.to_string(),
+    }
+}
+
+// Keeps the `max_groups` largest groups and folds the rest into a single
+// group labelled `other`, to bound the number of series per target.
+fn cap_groups(counts: HashMap<Vec<String>, f64>, max_groups: usize) -> Vec<(Vec<String>, f64)> {
+    let mut groups: Vec<(Vec<String>, f64)> = counts.into_iter().collect();
+    if groups.len() <= max_groups {
+        return groups;
+    }
+
+    // Taken before draining, which empties the groups when `max_groups` is 0
+    let label_count = groups[0].0.len();
+    groups.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let other: f64 = groups.drain(max_groups..).map(|(_, count)| count).sum();
+    groups.push((vec![OTHER.to_string(); label_count], other));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_are_grouped_and_capped() {
+        let list = "\
+id=1 addr=10.0.0.1:5000 name=api age=30 idle=0 flags=N user=default tot-mem=2000 omem=0 lib-name=redis-rs lib-ver=0.27.5
+id=2 addr=10.0.0.2:5001 name=api age=600 idle=120 flags=N user=default tot-mem=9000 omem=4096 lib-name=redis-rs lib-ver=0.27.5
+id=3 addr=10.0.1.3:5002 name=worker age=7200 idle=7000 flags=P user=app tot-mem=1000 omem=0 lib-name= lib-ver=";
+        let clients: Vec<Client> = list.lines().filter_map(Client::parse).collect();
+        assert_eq!(clients.len(), 3);
+        assert_eq!(source(clients[0].addr, true), "10.0.0.0/24");
+        assert_eq!(source("[::1]:6379", false), "::1");
+
+        let mut counts = HashMap::new();
+        for client in &clients {
+            *counts.entry(vec![client.name.to_string()]).or_default() += 1f64;
+        }
+        let mut groups = cap_groups(counts, 1);
+        groups.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            groups,
+            vec![
+                (vec!["api".to_string()], 2f64),
+                (vec![OTHER.to_string()], 1f64),
+            ]
+        );
+
+        // Every client is folded into `other` without a group
+        let mut counts = HashMap::new();
+        for client in &clients {
+            let labels = vec![client.lib_name.to_string(), client.lib_ver.to_string()];
+            *counts.entry(labels).or_default() += 1f64;
+        }
+        assert_eq!(
+            cap_groups(counts, 0),
+            vec![(vec![OTHER.to_string(); 2], 3f64)]
+        );
+
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let config = Config {
+            top_n: 1,
+            max_groups: 0,
+            ..Config::default()
+        };
+        metrics.update("t", "t", &config, &clients);
+        let top: Vec<_> = registry
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == "redis_client_top_total_memory_bytes")
+            .unwrap()
+            .get_metric()
+            .iter()
+            .map(|m| m.get_gauge().get_value())
+            .collect();
+        assert_eq!(top, vec![9000f64]);
+
+        let idle = |le: &str| {
+            registry
+                .gather()
+                .into_iter()
+                .find(|family| family.get_name() == "redis_clients_idle_seconds_bucket_count")
+                .unwrap()
+                .get_metric()
+                .iter()
+                .find(|m| {
+                    m.get_label()
+                        .iter()
+                        .any(|l| l.get_name() == "le" && l.get_value() == le)
+                })
+                .map(|m| m.get_gauge().get_value())
+        };
+        assert_eq!(idle("60"), Some(1f64));
+        assert_eq!(idle("300"), Some(2f64));
+        assert_eq!(idle("+Inf"), Some(3f64));
+    }
+}
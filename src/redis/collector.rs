@@ -0,0 +1,30 @@
+use crate::redis::connection;
+use redis::RedisResult;
+use std::future::Future;
+
+// Connection and labels of a target, shared by all of its collectors.
+#[derive(Clone)]
+pub struct Target {
+    pub conn: connection::Connection,
+    pub url: String,
+    pub name: String,
+}
+
+impl Target {
+    pub fn new(config: &connection::Config) -> RedisResult<Self> {
+        Ok(Self {
+            conn: connection::Connection::new(config)?,
+            url: config.url.clone(),
+            name: config.target_name().to_string(),
+        })
+    }
+}
+
+pub trait Collect: Send + Sync + 'static {
+    // Name of the collector in logs
+    const NAME: &'static str;
+
+    fn target(&self) -> &Target;
+
+    fn collect(&self) -> impl Future<Output = RedisResult<()>> + Send;
+}
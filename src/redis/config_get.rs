@@ -0,0 +1,275 @@
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::{debug, warn};
+use prometheus::{opts, register_gauge_vec_with_registry, GaugeVec, Registry};
+use redis::{RedisResult, Value};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const CLIENT_OUTPUT_BUFFER_LIMIT: &str = "client-output-buffer-limit";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub parameters: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            parameters: [
+                "maxclients",
+                "maxmemory",
+                "timeout",
+                "hz",
+                "io-threads",
+                "repl-backlog-size",
+                CLIENT_OUTPUT_BUFFER_LIMIT,
+                "maxmemory-policy",
+                "appendfsync",
+                "save",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+pub struct Collector {
+    target: Target,
+    config: Config,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>, config_get: &Config) -> Self {
+        Self {
+            target,
+            config: config_get.clone(),
+            metrics,
+        }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "config";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        // One CONFIG GET per parameter, as Redis before 7 only accepts one
+        let mut pipe = redis::pipe();
+        for parameter in &self.config.parameters {
+            pipe.cmd("CONFIG").arg("GET").arg(parameter);
+        }
+        let replies: Vec<Value> = pipe.query_async(&mut self.target.conn.get().await?).await?;
+
+        debug!("Receive data from CONFIG GET command");
+        let parameters: Vec<(String, String)> = replies
+            .iter()
+            .flat_map(reply::as_pairs)
+            .filter_map(|(name, value)| Some((reply::as_string(name)?, reply::as_string(value)?)))
+            .collect();
+        self.metrics
+            .update(&self.target.url, &self.target.name, &parameters);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    registry: Registry,
+    // `redis_config_<name>` gauges, registered the first time a numeric
+    // parameter is seen since the parameter list is configurable.
+    numeric: Mutex<HashMap<String, GaugeVec>>,
+    info: SnapshotGaugeVec,
+    output_buffer_hard_limit: SnapshotGaugeVec,
+    output_buffer_soft_limit: SnapshotGaugeVec,
+    output_buffer_soft_seconds: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            registry: registry.clone(),
+            numeric: Mutex::new(HashMap::new()),
+            info: SnapshotGaugeVec::new(
+                opts!(
+                    "config_info",
+                    "Value of a non-numeric configuration parameter, always 1"
+                )
+                .namespace("redis"),
+                &["parameter", "value", "target", "target_name"],
+                registry,
+            )?,
+            output_buffer_hard_limit: SnapshotGaugeVec::new(
+                opts!(
+                    "config_client_output_buffer_limit_hard_bytes",
+                    "Hard limit of the client output buffer per client class"
+                )
+                .namespace("redis"),
+                &["class", "target", "target_name"],
+                registry,
+            )?,
+            output_buffer_soft_limit: SnapshotGaugeVec::new(
+                opts!(
+                    "config_client_output_buffer_limit_soft_bytes",
+                    "Soft limit of the client output buffer per client class"
+                )
+                .namespace("redis"),
+                &["class", "target", "target_name"],
+                registry,
+            )?,
+            output_buffer_soft_seconds: SnapshotGaugeVec::new(
+                opts!(
+                    "config_client_output_buffer_limit_soft_seconds",
+                    "Time the soft limit of the client output buffer may be exceeded per client class"
+                )
+                .namespace("redis"),
+                &["class", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+
+    fn update(&self, target: &str, target_name: &str, parameters: &[(String, String)]) {
+        let mut info = Vec::new();
+        for (name, value) in parameters {
+            if name == CLIENT_OUTPUT_BUFFER_LIMIT {
+                self.update_output_buffer_limits(target, target_name, value);
+            } else if let Ok(number) = value.parse::<f64>() {
+                match self.numeric_gauge(name) {
+                    Ok(gauge) => gauge.with_label_values(&[target, target_name]).set(number),
+                    Err(e) => warn!("Unable to export configuration parameter {}: {}", name, e),
+                }
+            } else {
+                info.push((
+                    vec![
+                        name.clone(),
+                        value.clone(),
+                        target.to_string(),
+                        target_name.to_string(),
+                    ],
+                    1f64,
+                ));
+            }
+        }
+        self.info.set_target(target, info);
+    }
+
+    fn numeric_gauge(&self, parameter: &str) -> Result<GaugeVec, prometheus::Error> {
+        let mut numeric = self.numeric.lock().unwrap();
+        if let Some(gauge) = numeric.get(parameter) {
+            return Ok(gauge.clone());
+        }
+
+        let name = format!("config_{}", parameter.replace(['-', '.'], "_"));
+        let help = format!("Value of the {} configuration parameter", parameter);
+        let gauge = register_gauge_vec_with_registry!(
+            opts!(name, help).namespace("redis"),
+            &["target", "target_name"],
+            self.registry
+        )?;
+        numeric.insert(parameter.to_string(), gauge.clone());
+        Ok(gauge)
+    }
+
+    // `normal 0 0 0 replica 268435456 67108864 60 pubsub 33554432 8388608 60`
+    fn update_output_buffer_limits(&self, target: &str, target_name: &str, value: &str) {
+        let mut hard = Vec::new();
+        let mut soft = Vec::new();
+        let mut soft_seconds = Vec::new();
+        let tokens: Vec<&str> = value.split_whitespace().collect();
+        for limit in tokens.chunks_exact(4) {
+            let labels = vec![
+                limit[0].to_string(),
+                target.to_string(),
+                target_name.to_string(),
+            ];
+            let (Ok(h), Ok(s), Ok(seconds)) = (
+                limit[1].parse::<f64>(),
+                limit[2].parse::<f64>(),
+                limit[3].parse::<f64>(),
+            ) else {
+                continue;
+            };
+            hard.push((labels.clone(), h));
+            soft.push((labels.clone(), s));
+            soft_seconds.push((labels, seconds));
+        }
+        self.output_buffer_hard_limit.set_target(target, hard);
+        self.output_buffer_soft_limit.set_target(target, soft);
+        self.output_buffer_soft_seconds
+            .set_target(target, soft_seconds);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parameters_are_exported_by_type() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let parameters: Vec<(String, String)> = [
+            ("maxclients", "10000"),
+            ("io-threads", "4"),
+            ("maxmemory-policy", "allkeys-lru"),
+            (
+                CLIENT_OUTPUT_BUFFER_LIMIT,
+                "normal 0 0 0 replica 268435456 67108864 60 pubsub 33554432 8388608 60",
+            ),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .to_vec();
+        metrics.update("t", "t", &parameters);
+        // Numeric gauges are only registered once
+        metrics.update("t", "t", &parameters);
+
+        let families = registry.gather();
+        let value = |name: &str, label: Option<(&str, &str)>| {
+            families
+                .iter()
+                .find(|family| family.get_name() == name)
+                .unwrap()
+                .get_metric()
+                .iter()
+                .find(|metric| {
+                    label.is_none_or(|(name, value)| {
+                        metric
+                            .get_label()
+                            .iter()
+                            .any(|l| l.get_name() == name && l.get_value() == value)
+                    })
+                })
+                .unwrap()
+                .get_gauge()
+                .get_value()
+        };
+        assert_eq!(value("redis_config_maxclients", None), 10000f64);
+        assert_eq!(value("redis_config_io_threads", None), 4f64);
+        assert_eq!(
+            value("redis_config_info", Some(("value", "allkeys-lru"))),
+            1f64
+        );
+        assert_eq!(
+            value(
+                "redis_config_client_output_buffer_limit_hard_bytes",
+                Some(("class", "replica"))
+            ),
+            268435456f64
+        );
+        assert_eq!(
+            value(
+                "redis_config_client_output_buffer_limit_soft_seconds",
+                Some(("class", "pubsub"))
+            ),
+            60f64
+        );
+    }
+}
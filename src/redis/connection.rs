@@ -1,7 +1,12 @@
-use redis::aio::MultiplexedConnection;
+use crate::redis::drift::Baseline;
+use crate::redis::{clients, config_get, malloc_stats, slowlog};
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
 use redis::{Client, RedisResult};
 use serde::Deserialize;
-use std::time::Duration;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
@@ -10,6 +15,28 @@ pub struct Config {
     pub url: String,
     pub response_timeout_ms: u64,
     pub connection_timeout_ms: u64,
+    pub latency_percentiles: Option<Vec<f64>>,
+    pub latency_histogram: bool,
+    pub latency_monitor: bool,
+    pub zero_fill_databases: bool,
+    pub memory_stats: bool,
+    pub security_checks: bool,
+    pub acl: bool,
+    pub check_keys: Vec<String>,
+    pub check_keys_limit: usize,
+    pub check_keys_scan_limit: usize,
+    pub malloc_stats: Option<malloc_stats::Config>,
+    pub client_list: Option<clients::Config>,
+    pub config_get: Option<config_get::Config>,
+    pub baseline_group: Option<String>,
+    pub baseline: Baseline,
+    pub slowlog: Option<slowlog::Config>,
+}
+
+impl Config {
+    pub fn target_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.url)
+    }
 }
 
 impl Default for Config {
@@ -19,16 +46,80 @@ impl Default for Config {
             url: "redis://127.0.0.1:6379".to_string(),
             response_timeout_ms: 5000,
             connection_timeout_ms: 5000,
+            latency_percentiles: None,
+            latency_histogram: false,
+            latency_monitor: false,
+            zero_fill_databases: false,
+            memory_stats: false,
+            security_checks: false,
+            acl: false,
+            check_keys: vec![],
+            check_keys_limit: 1000,
+            check_keys_scan_limit: 100,
+            malloc_stats: None,
+            client_list: None,
+            config_get: None,
+            baseline_group: None,
+            baseline: Baseline::default(),
+            slowlog: None,
         }
     }
 }
 
-pub async fn get_connection(config: Config) -> RedisResult<MultiplexedConnection> {
-    let client = Client::open(config.url.clone())?;
-    client
-        .get_multiplexed_tokio_connection_with_response_timeouts(
-            Duration::from_millis(config.response_timeout_ms),
-            Duration::from_millis(config.connection_timeout_ms),
-        )
-        .await
+// Connection to a target, established by the first collection that needs it
+// so an unreachable target is reported by its collectors instead of stopping
+// the exporter. The manager then reconnects on its own.
+#[derive(Clone)]
+pub struct Connection {
+    client: Client,
+    manager_config: ConnectionManagerConfig,
+    manager: Arc<OnceCell<ConnectionManager>>,
+    connection_timeout: Duration,
+    failed_at: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Connection {
+    pub fn new(config: &Config) -> RedisResult<Self> {
+        let connection_timeout = Duration::from_millis(config.connection_timeout_ms);
+        Ok(Self {
+            client: Client::open(config.url.clone())?,
+            // A failed connection is retried by the next collection, not
+            // within this one
+            manager_config: ConnectionManagerConfig::new()
+                .set_number_of_retries(0)
+                .set_response_timeout(Duration::from_millis(config.response_timeout_ms))
+                .set_connection_timeout(connection_timeout),
+            manager: Arc::new(OnceCell::new()),
+            connection_timeout,
+            failed_at: Arc::new(Mutex::new(None)),
+        })
+    }
+
+    pub async fn get(&self) -> RedisResult<ConnectionManager> {
+        self.manager
+            .get_or_try_init(|| async {
+                // Collectors of the target share one attempt per connection
+                // timeout instead of each waiting for their own
+                let failed_at = *self.failed_at.lock().unwrap();
+                if failed_at.is_some_and(|at| at.elapsed() < self.connection_timeout) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotConnected,
+                        "target unreachable, connection will be retried",
+                    )
+                    .into());
+                }
+
+                let result = ConnectionManager::new_with_config(
+                    self.client.clone(),
+                    self.manager_config.clone(),
+                )
+                .await;
+                if result.is_err() {
+                    *self.failed_at.lock().unwrap() = Some(Instant::now());
+                }
+                result
+            })
+            .await
+            .cloned()
+    }
 }
@@ -0,0 +1,210 @@
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::{debug, info, warn};
+use prometheus::{opts, Registry};
+use redis::{RedisResult, Value};
+use serde::{de, Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+// Expected configuration parameters. Values may be written as YAML numbers or
+// booleans (`appendonly: no`), and memory sizes with units (`maxmemory: 1gb`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Baseline(pub BTreeMap<String, String>);
+
+impl Baseline {
+    // Parameters of `overrides` take precedence over the ones of `self`.
+    pub fn merge(&self, overrides: &Baseline) -> Baseline {
+        let mut merged = self.0.clone();
+        merged.extend(overrides.0.clone());
+        Baseline(merged)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'de> Deserialize<'de> for Baseline {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let parameters = BTreeMap::<String, serde_yml::Value>::deserialize(deserializer)?;
+        parameters
+            .into_iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    serde_yml::Value::String(s) => s,
+                    serde_yml::Value::Number(n) => n.to_string(),
+                    serde_yml::Value::Bool(true) => "yes".to_string(),
+                    serde_yml::Value::Bool(false) => "no".to_string(),
+                    _ => {
+                        return Err(de::Error::custom(format!(
+                            "baseline value of {} must be a string, number or boolean",
+                            name
+                        )))
+                    }
+                };
+                Ok((name, value))
+            })
+            .collect::<Result<_, _>>()
+            .map(Baseline)
+    }
+}
+
+pub struct Collector {
+    target: Target,
+    baseline: Baseline,
+    // Actual value of each drifted parameter, so diffs are logged once
+    drifted: Mutex<HashMap<String, String>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>, baseline: &Baseline) -> Self {
+        Self {
+            target,
+            baseline: baseline.clone(),
+            drifted: Mutex::new(HashMap::new()),
+            metrics,
+        }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "config drift";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let mut pipe = redis::pipe();
+        for parameter in self.baseline.0.keys() {
+            pipe.cmd("CONFIG").arg("GET").arg(parameter);
+        }
+        let replies: Vec<Value> = pipe.query_async(&mut self.target.conn.get().await?).await?;
+
+        debug!("Receive data from CONFIG GET command for drift detection");
+        let actual: HashMap<String, String> = replies
+            .iter()
+            .flat_map(reply::as_pairs)
+            .filter_map(|(name, value)| Some((reply::as_string(name)?, reply::as_string(value)?)))
+            .collect();
+
+        let mut series = Vec::with_capacity(self.baseline.0.len());
+        let mut drifted = self.drifted.lock().unwrap();
+        for (parameter, expected) in &self.baseline.0 {
+            // A parameter unknown to Redis is reported with an empty value
+            let actual = actual.get(parameter).cloned().unwrap_or_default();
+            let drift = !matches(expected, &actual);
+            if drift && drifted.get(parameter) != Some(&actual) {
+                warn!(
+                    "Configuration of {} drifted from its baseline: {} is {:?}, expected {:?}",
+                    &self.target.name, parameter, actual, expected
+                );
+                drifted.insert(parameter.clone(), actual.clone());
+            } else if !drift && drifted.remove(parameter).is_some() {
+                info!(
+                    "Configuration of {} is back to its baseline: {} is {:?}",
+                    &self.target.name, parameter, actual
+                );
+            }
+
+            series.push((
+                vec![
+                    parameter.clone(),
+                    expected.clone(),
+                    actual,
+                    self.target.url.clone(),
+                    self.target.name.clone(),
+                ],
+                if drift { 1f64 } else { 0f64 },
+            ));
+        }
+        self.metrics.drift.set_target(&self.target.url, series);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    drift: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            drift: SnapshotGaugeVec::new(
+                opts!(
+                    "config_drift",
+                    "Whether a configuration parameter differs from its baseline (1) or not (0)"
+                )
+                .namespace("redis"),
+                &["parameter", "expected", "actual", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+}
+
+// Compares values the way Redis would read them: case and spacing are
+// ignored and memory sizes are compared in bytes.
+fn matches(expected: &str, actual: &str) -> bool {
+    normalize(expected) == normalize(actual)
+}
+
+fn normalize(value: &str) -> String {
+    let value = value
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    memory_bytes(&value).map_or(value, |bytes| bytes.to_string())
+}
+
+// Redis memory units: `k`, `m` and `g` are powers of 1000, `kb`, `mb` and
+// `gb` powers of 1024.
+fn memory_bytes(value: &str) -> Option<u64> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = value.split_at(split);
+    let multiplier = match unit {
+        "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_values_are_normalized() {
+        let baseline: Baseline = serde_yml::from_str(
+            "maxmemory: 1gb\nmaxclients: 10000\nappendonly: no\nsave: \"3600 1  300 100\"",
+        )
+        .unwrap();
+        let expected = |name: &str| baseline.0[name].as_str();
+
+        assert!(matches(expected("maxmemory"), "1073741824"));
+        assert!(matches(expected("maxclients"), "10000"));
+        assert!(matches(expected("appendonly"), "no"));
+        assert!(!matches(expected("appendonly"), "yes"));
+        assert!(matches(expected("save"), "3600 1 300 100"));
+        assert!(!matches(expected("save"), ""));
+
+        let group = Baseline(BTreeMap::from([
+            ("maxclients".to_string(), "5000".to_string()),
+            ("hz".to_string(), "10".to_string()),
+        ]));
+        let merged = group.merge(&baseline);
+        assert_eq!(merged.0["maxclients"], "10000");
+        assert_eq!(merged.0["hz"], "10");
+    }
+}
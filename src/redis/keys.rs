@@ -0,0 +1,321 @@
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::{connection, reply};
+use log::{debug, info, warn};
+use prometheus::{opts, Registry};
+use redis::aio::ConnectionManager;
+use redis::{IntoConnectionInfo, RedisResult, Value};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+const SCAN_COUNT: usize = 1000;
+
+pub struct Collector {
+    target: Target,
+    db: String,
+    check_keys: Vec<String>,
+    limit: usize,
+    scan_limit: usize,
+    // Limits reached by the previous collection, logged when they change
+    limits_reached: Mutex<LimitsReached>,
+    metrics: Arc<Metrics>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct LimitsReached {
+    keys: bool,
+    scans: bool,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>, config: &connection::Config) -> Self {
+        // Keys are checked in the database selected by the target URL
+        let db = config
+            .url
+            .as_str()
+            .into_connection_info()
+            .map(|info| info.redis.db)
+            .unwrap_or(0);
+        Self {
+            target,
+            db: db.to_string(),
+            check_keys: config.check_keys.clone(),
+            limit: config.check_keys_limit,
+            scan_limit: config.check_keys_scan_limit,
+            limits_reached: Mutex::new(LimitsReached::default()),
+            metrics,
+        }
+    }
+
+    // Exact names are checked as is, glob patterns are matched with SCAN until
+    // the limit of keys or of SCAN calls is reached.
+    async fn matching_keys(&self, conn: &mut ConnectionManager) -> RedisResult<Vec<String>> {
+        let mut keys = BTreeSet::new();
+        let mut scans = 0;
+        let mut reached = LimitsReached::default();
+        for check_key in &self.check_keys {
+            if !is_pattern(check_key) {
+                keys.insert(check_key.clone());
+            } else {
+                let mut cursor = 0u64;
+                loop {
+                    if scans >= self.scan_limit {
+                        reached.scans = true;
+                        break;
+                    }
+                    let (next, matched): (u64, Vec<String>) = redis::cmd("SCAN")
+                        .arg(cursor)
+                        .arg("MATCH")
+                        .arg(check_key)
+                        .arg("COUNT")
+                        .arg(SCAN_COUNT)
+                        .query_async(conn)
+                        .await?;
+                    scans += 1;
+                    keys.extend(matched);
+                    cursor = next;
+                    if cursor == 0 || keys.len() >= self.limit {
+                        break;
+                    }
+                }
+            }
+
+            if keys.len() >= self.limit {
+                reached.keys = true;
+                break;
+            }
+        }
+        self.log_limits(reached);
+        Ok(keys.into_iter().take(self.limit).collect())
+    }
+
+    fn log_limits(&self, reached: LimitsReached) {
+        let mut previous = self.limits_reached.lock().unwrap();
+        if reached.keys && !previous.keys {
+            warn!(
+                "check_keys_limit of {} keys reached for {}, other matching keys are not checked",
+                self.limit, &self.target.name
+            );
+        }
+        if reached.scans && !previous.scans {
+            warn!(
+                "check_keys_scan_limit of {} SCAN calls reached for {}, keys beyond it are not matched",
+                self.scan_limit, &self.target.name
+            );
+        }
+        if (previous.keys || previous.scans) && !(reached.keys || reached.scans) {
+            info!(
+                "Key checks of {} are back under their limits",
+                &self.target.name
+            );
+        }
+        *previous = reached;
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "key checks";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let mut conn = self.target.conn.get().await?;
+        let keys = self.matching_keys(&mut conn).await?;
+
+        let mut pipe = redis::pipe();
+        for key in &keys {
+            pipe.cmd("TYPE").arg(key);
+        }
+        let types: Vec<String> = if keys.is_empty() {
+            vec![]
+        } else {
+            pipe.query_async(&mut conn).await?
+        };
+
+        // Keys deleted since SCAN have the type `none` and are skipped
+        let keys: Vec<(String, String)> = keys
+            .into_iter()
+            .zip(types)
+            .filter(|(_, key_type)| key_type != "none")
+            .collect();
+        let mut pipe = redis::pipe();
+        for (key, key_type) in &keys {
+            // ECHO keeps three replies per key for types without a length
+            match size_command(key_type) {
+                Some(command) => pipe.cmd(command).arg(key),
+                None => pipe.cmd("ECHO").arg(""),
+            };
+            pipe.cmd("PTTL").arg(key);
+            pipe.cmd("MEMORY").arg("USAGE").arg(key);
+        }
+        let replies: Vec<Value> = if keys.is_empty() {
+            vec![]
+        } else {
+            pipe.query_async(&mut conn).await?
+        };
+
+        debug!("Receive data from key checks");
+        let checks: Vec<KeyCheck> = keys
+            .into_iter()
+            .zip(replies.chunks_exact(3))
+            .map(|((key, key_type), replies)| KeyCheck {
+                size: size_command(&key_type).and_then(|_| reply::as_f64(&replies[0])),
+                // PTTL is -1 without expiry, and -2 if the key was just deleted
+                ttl: reply::as_f64(&replies[1])
+                    .filter(|ttl| *ttl >= 0f64)
+                    .map(|ttl| ttl / 1000.0),
+                memory_usage: reply::as_f64(&replies[2]),
+                key,
+                key_type,
+            })
+            .collect();
+        self.metrics
+            .update(&self.target.url, &self.target.name, &self.db, &checks);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    key_info: SnapshotGaugeVec,
+    key_size: SnapshotGaugeVec,
+    key_ttl: SnapshotGaugeVec,
+    key_memory_usage: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            key_info: SnapshotGaugeVec::new(
+                opts!("key_info", "Type of a checked key, always 1").namespace("redis"),
+                &["db", "key", "type", "target", "target_name"],
+                registry,
+            )?,
+            key_size: SnapshotGaugeVec::new(
+                opts!(
+                    "key_size",
+                    "Length of a checked key, in elements or bytes for strings"
+                )
+                .namespace("redis"),
+                &["db", "key", "target", "target_name"],
+                registry,
+            )?,
+            key_ttl: SnapshotGaugeVec::new(
+                opts!(
+                    "key_ttl_seconds",
+                    "Time to live of a checked key with an expiry"
+                )
+                .namespace("redis"),
+                &["db", "key", "target", "target_name"],
+                registry,
+            )?,
+            key_memory_usage: SnapshotGaugeVec::new(
+                opts!(
+                    "key_memory_usage_bytes",
+                    "Memory used by a checked key as reported by MEMORY USAGE"
+                )
+                .namespace("redis"),
+                &["db", "key", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+
+    fn update(&self, target: &str, target_name: &str, db: &str, checks: &[KeyCheck]) {
+        let labels = |key: &str| {
+            vec![
+                db.to_string(),
+                key.to_string(),
+                target.to_string(),
+                target_name.to_string(),
+            ]
+        };
+        let series = |value: fn(&KeyCheck) -> Option<f64>| {
+            checks
+                .iter()
+                .filter_map(|check| Some((labels(&check.key), value(check)?)))
+                .collect()
+        };
+
+        self.key_info.set_target(
+            target,
+            checks
+                .iter()
+                .map(|check| {
+                    let mut labels = labels(&check.key);
+                    labels.insert(2, check.key_type.clone());
+                    (labels, 1f64)
+                })
+                .collect(),
+        );
+        self.key_size.set_target(target, series(|c| c.size));
+        self.key_ttl.set_target(target, series(|c| c.ttl));
+        self.key_memory_usage
+            .set_target(target, series(|c| c.memory_usage));
+    }
+}
+
+struct KeyCheck {
+    key: String,
+    key_type: String,
+    size: Option<f64>,
+    ttl: Option<f64>,
+    memory_usage: Option<f64>,
+}
+
+fn is_pattern(key: &str) -> bool {
+    key.contains(['*', '?', '['])
+}
+
+fn size_command(key_type: &str) -> Option<&'static str> {
+    match key_type {
+        "string" => Some("STRLEN"),
+        "list" => Some("LLEN"),
+        "set" => Some("SCARD"),
+        "zset" => Some("ZCARD"),
+        "hash" => Some("HLEN"),
+        "stream" => Some("XLEN"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_checked_by_type() {
+        assert!(is_pattern("queue:*"));
+        assert!(is_pattern("cache:[ab]"));
+        assert!(!is_pattern("queue:emails"));
+
+        assert_eq!(size_command("list"), Some("LLEN"));
+        assert_eq!(size_command("stream"), Some("XLEN"));
+        assert_eq!(size_command("ReJSON-RL"), None);
+
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let check = |key: &str, ttl| KeyCheck {
+            key: key.to_string(),
+            key_type: "list".to_string(),
+            size: Some(3f64),
+            ttl,
+            memory_usage: Some(64f64),
+        };
+        metrics.update("t", "t", "0", &[check("a", Some(10f64)), check("b", None)]);
+        metrics.update("t", "t", "0", &[check("b", None)]);
+
+        let families = registry.gather();
+        let series = |name: &str| {
+            families
+                .iter()
+                .find(|family| family.get_name() == name)
+                .map_or(0, |family| family.get_metric().len())
+        };
+        assert_eq!(series("redis_key_size"), 1);
+        assert_eq!(series("redis_key_ttl_seconds"), 0);
+        assert_eq!(series("redis_key_info"), 1);
+    }
+}
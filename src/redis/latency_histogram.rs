@@ -0,0 +1,206 @@
+use crate::prometheus::histogram::{HistogramSnapshot, SnapshotHistogramVec};
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::debug;
+use prometheus::Registry;
+use redis::{RedisResult, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Redis tracks command latencies in power-of-two microsecond buckets up to
+// roughly one second, so every histogram is exported over the same bounds.
+const MAX_BUCKET_EXPONENT: u32 = 20;
+
+pub struct Collector {
+    target: Target,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>) -> Self {
+        Self { target, metrics }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "latency histogram";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let (histograms, commandstats): (Value, String) = redis::pipe()
+            .cmd("LATENCY")
+            .arg("HISTOGRAM")
+            .cmd("INFO")
+            .arg("commandstats")
+            .query_async(&mut self.target.conn.get().await?)
+            .await?;
+
+        debug!("Receive data from LATENCY HISTOGRAM command");
+        let durations = parse_command_durations(&commandstats);
+        let series = parse_histograms(&histograms)
+            .into_iter()
+            .map(|(cmd, mut snapshot)| {
+                snapshot.sum = durations.get(&cmd).copied().unwrap_or(0f64);
+                (
+                    vec![cmd, self.target.url.clone(), self.target.name.clone()],
+                    snapshot,
+                )
+            })
+            .collect();
+        self.metrics
+            .command_latency
+            .set_target(&self.target.url, series);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    command_latency: SnapshotHistogramVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let command_latency = SnapshotHistogramVec::new(
+            "redis",
+            "command_latency_seconds",
+            "Latency distribution per command as reported by LATENCY HISTOGRAM",
+            &["cmd", "target", "target_name"],
+        )?;
+        registry.register(Box::new(command_latency.clone()))?;
+
+        Ok(Self { command_latency })
+    }
+}
+
+fn parse_histograms(reply: &Value) -> Vec<(String, HistogramSnapshot)> {
+    let mut histograms = Vec::new();
+    for (cmd, details) in reply::as_pairs(reply) {
+        let Some(cmd) = reply::as_string(cmd) else {
+            continue;
+        };
+
+        let mut calls = 0u64;
+        let mut reported = Vec::new();
+        for (key, value) in reply::as_pairs(details) {
+            match reply::as_string(key).as_deref() {
+                Some("calls") => calls = reply::as_f64(value).unwrap_or(0f64) as u64,
+                Some("histogram_usec") => {
+                    reported = reply::as_pairs(value)
+                        .into_iter()
+                        .filter_map(|(bound, count)| {
+                            Some((reply::as_f64(bound)?, reply::as_f64(count)? as u64))
+                        })
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+
+        let buckets = (0..=MAX_BUCKET_EXPONENT)
+            .map(|exponent| {
+                let bound = 2u64.pow(exponent) as f64;
+                let count = reported
+                    .iter()
+                    .filter(|(reported_bound, _)| *reported_bound <= bound)
+                    .map(|(_, count)| *count)
+                    .max()
+                    .unwrap_or(0);
+                (bound / 1_000_000.0, count)
+            })
+            .collect();
+
+        histograms.push((
+            cmd,
+            HistogramSnapshot {
+                buckets,
+                count: calls,
+                sum: 0f64,
+            },
+        ));
+    }
+    histograms
+}
+
+fn parse_command_durations(commandstats: &str) -> HashMap<String, f64> {
+    commandstats
+        .lines()
+        .filter_map(|line| {
+            let (metric, value) = line.split_once(':')?;
+            let cmd = metric.strip_prefix("cmdstat_")?;
+            let usec = value
+                .split(',')
+                .filter_map(|s| s.split_once('='))
+                .find(|(key, _)| *key == "usec")?
+                .1
+                .parse::<f64>()
+                .ok()?;
+            Some((cmd.to_string(), usec / 1_000_000.0))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{Encoder, TextEncoder};
+
+    fn bulk(s: &str) -> Value {
+        Value::BulkString(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn histograms_are_exported_over_fixed_buckets() {
+        let reply = Value::Array(vec![
+            bulk("set"),
+            Value::Array(vec![
+                bulk("calls"),
+                Value::Int(100),
+                bulk("histogram_usec"),
+                Value::Array(vec![
+                    Value::Int(1),
+                    Value::Int(90),
+                    Value::Int(4),
+                    Value::Int(99),
+                    Value::Int(16),
+                    Value::Int(100),
+                ]),
+            ]),
+        ]);
+
+        let histograms = parse_histograms(&reply);
+        assert_eq!(histograms.len(), 1);
+        let (cmd, snapshot) = &histograms[0];
+        assert_eq!(cmd, "set");
+        assert_eq!(snapshot.count, 100);
+        assert_eq!(snapshot.buckets.len(), MAX_BUCKET_EXPONENT as usize + 1);
+        assert_eq!(snapshot.buckets[0], (0.000_001, 90));
+        assert_eq!(snapshot.buckets[1], (0.000_002, 90));
+        assert_eq!(snapshot.buckets[2], (0.000_004, 99));
+        assert_eq!(snapshot.buckets[4], (0.000_016, 100));
+
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        metrics.command_latency.set_target(
+            "t",
+            vec![(
+                vec!["set".to_string(), "t".to_string(), "t".to_string()],
+                snapshot.clone(),
+            )],
+        );
+        let mut buffer = vec![];
+        TextEncoder::new()
+            .encode(&registry.gather(), &mut buffer)
+            .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains(
+            "redis_command_latency_seconds_bucket{cmd=\"set\",target=\"t\",target_name=\"t\",le=\"0.000004\"} 99"
+        ));
+        assert!(text.contains(
+            "redis_command_latency_seconds_count{cmd=\"set\",target=\"t\",target_name=\"t\"} 100"
+        ));
+    }
+}
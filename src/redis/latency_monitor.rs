@@ -0,0 +1,234 @@
+use crate::prometheus::series::SeriesTracker;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::debug;
+use prometheus::{
+    opts, register_counter_vec_with_registry, register_gauge_vec_with_registry, CounterVec,
+    GaugeVec, Registry,
+};
+use redis::{RedisResult, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+pub struct Collector {
+    target: Target,
+    // Timestamp of the latest spike counted per event, None until the first
+    // collection has recorded a baseline.
+    last_spikes: Mutex<Option<HashMap<String, u64>>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>) -> Self {
+        Self {
+            target,
+            last_spikes: Mutex::new(None),
+            metrics,
+        }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "latency monitor";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let mut conn = self.target.conn.get().await?;
+        let latest: Value = redis::cmd("LATENCY")
+            .arg("LATEST")
+            .query_async(&mut conn)
+            .await?;
+
+        debug!("Receive data from LATENCY LATEST command");
+        let events = parse_latest(&latest);
+        let mut pipe = redis::pipe();
+        for event in &events {
+            pipe.cmd("LATENCY").arg("HISTORY").arg(&event.name);
+        }
+        let histories: Vec<Value> = if events.is_empty() {
+            vec![]
+        } else {
+            pipe.query_async(&mut conn).await?
+        };
+
+        let mut last_spikes = self.last_spikes.lock().unwrap();
+        let baseline = last_spikes.is_none();
+        let last_spikes = last_spikes.get_or_insert_with(HashMap::new);
+        let new_spikes: Vec<u64> = events
+            .iter()
+            .zip(&histories)
+            .map(|(event, history)| {
+                let last = last_spikes.entry(event.name.clone()).or_default();
+                let new_spikes = count_new_spikes(last, &parse_history(history));
+                if baseline {
+                    0
+                } else {
+                    new_spikes
+                }
+            })
+            .collect();
+
+        self.metrics
+            .update(&self.target.url, &self.target.name, &events, &new_spikes);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    spike_last: GaugeVec,
+    spike_max: GaugeVec,
+    spikes_total: CounterVec,
+    event_series: SeriesTracker,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            spike_last: register_gauge_vec_with_registry!(
+                opts!(
+                    "latency_spike_last_seconds",
+                    "Unix timestamp of the latest latency spike per event"
+                )
+                .namespace("redis"),
+                &["event", "target", "target_name"],
+                registry
+            )?,
+            spike_max: register_gauge_vec_with_registry!(
+                opts!(
+                    "latency_spike_max_ms",
+                    "Highest latency in milliseconds recorded per event"
+                )
+                .namespace("redis"),
+                &["event", "target", "target_name"],
+                registry
+            )?,
+            spikes_total: register_counter_vec_with_registry!(
+                opts!(
+                    "latency_spikes_total",
+                    "Total number of latency spikes per event seen by the exporter"
+                )
+                .namespace("redis"),
+                &["event", "target", "target_name"],
+                registry
+            )?,
+            event_series: SeriesTracker::new(),
+        })
+    }
+
+    fn update(&self, target: &str, target_name: &str, events: &[Event], new_spikes: &[u64]) {
+        let mut current = HashSet::new();
+        for (event, new_spikes) in events.iter().zip(new_spikes) {
+            let labels = [event.name.as_str(), target, target_name];
+            self.spike_last
+                .with_label_values(&labels)
+                .set(event.timestamp as f64);
+            self.spike_max
+                .with_label_values(&labels)
+                .set(event.max_ms as f64);
+            self.spikes_total
+                .with_label_values(&labels)
+                .inc_by(*new_spikes as f64);
+            current.insert(labels.map(String::from).to_vec());
+        }
+        // Events are dropped by LATENCY RESET
+        self.event_series
+            .retain(target, current, &[&self.spike_last, &self.spike_max]);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Event {
+    name: String,
+    timestamp: u64,
+    max_ms: u64,
+}
+
+// Each entry of LATENCY LATEST is an array of event name, timestamp of the
+// latest spike, its latency and the highest latency, both in milliseconds.
+fn parse_latest(reply: &Value) -> Vec<Event> {
+    let Value::Array(events) = reply else {
+        return vec![];
+    };
+
+    events
+        .iter()
+        .filter_map(|event| {
+            let Value::Array(fields) = event else {
+                return None;
+            };
+            Some(Event {
+                name: reply::as_string(fields.first()?)?,
+                timestamp: reply::as_f64(fields.get(1)?)? as u64,
+                max_ms: reply::as_f64(fields.get(3)?)? as u64,
+            })
+        })
+        .collect()
+}
+
+// LATENCY HISTORY lists the timestamp and latency of up to 160 recent spikes.
+fn parse_history(reply: &Value) -> Vec<u64> {
+    let Value::Array(samples) = reply else {
+        return vec![];
+    };
+
+    samples
+        .iter()
+        .filter_map(|sample| match sample {
+            Value::Array(fields) => Some(reply::as_f64(fields.first()?)? as u64),
+            _ => None,
+        })
+        .collect()
+}
+
+fn count_new_spikes(last: &mut u64, timestamps: &[u64]) -> u64 {
+    let new_spikes = timestamps.iter().filter(|ts| **ts > *last).count() as u64;
+    *last = timestamps.iter().copied().max().unwrap_or(0).max(*last);
+    new_spikes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(values: &[i64]) -> Value {
+        Value::Array(values.iter().map(|v| Value::Int(*v)).collect())
+    }
+
+    #[test]
+    fn spikes_are_counted_once() {
+        let latest = Value::Array(vec![Value::Array(vec![
+            Value::BulkString(b"fork".to_vec()),
+            Value::Int(1_700_000_020),
+            Value::Int(120),
+            Value::Int(350),
+        ])]);
+        assert_eq!(
+            parse_latest(&latest),
+            vec![Event {
+                name: "fork".to_string(),
+                timestamp: 1_700_000_020,
+                max_ms: 350,
+            }]
+        );
+
+        let history = Value::Array(vec![
+            sample(&[1_700_000_000, 350]),
+            sample(&[1_700_000_010, 80]),
+        ]);
+        let mut last = 0;
+        assert_eq!(count_new_spikes(&mut last, &parse_history(&history)), 2);
+
+        let history = Value::Array(vec![
+            sample(&[1_700_000_000, 350]),
+            sample(&[1_700_000_010, 80]),
+            sample(&[1_700_000_020, 120]),
+        ]);
+        assert_eq!(count_new_spikes(&mut last, &parse_history(&history)), 1);
+        assert_eq!(count_new_spikes(&mut last, &[]), 0);
+        assert_eq!(last, 1_700_000_020);
+    }
+}
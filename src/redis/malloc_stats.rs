@@ -0,0 +1,356 @@
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use log::debug;
+use prometheus::{opts, Registry};
+use redis::RedisResult;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const GLOBAL_STATS: [&str; 6] = [
+    "allocated",
+    "active",
+    "metadata",
+    "resident",
+    "mapped",
+    "retained",
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub interval_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { interval_secs: 300 }
+    }
+}
+
+pub struct Collector {
+    target: Target,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>) -> Self {
+        Self { target, metrics }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "malloc stats";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let malloc_stats: String = redis::cmd("MEMORY")
+            .arg("MALLOC-STATS")
+            .query_async(&mut self.target.conn.get().await?)
+            .await?;
+
+        debug!("Receive data from MEMORY MALLOC-STATS command");
+        let stats = MallocStats::parse(&malloc_stats);
+        if stats.is_none() {
+            debug!(
+                "MEMORY MALLOC-STATS of {} is not jemalloc output, skipping",
+                &self.target.name
+            );
+        }
+        self.metrics.update(
+            &self.target.url,
+            &self.target.name,
+            &stats.unwrap_or_default(),
+        );
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    bytes: SnapshotGaugeVec,
+    arena_pages: SnapshotGaugeVec,
+    bin_allocated: SnapshotGaugeVec,
+    bin_regions: SnapshotGaugeVec,
+    bin_slabs: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            bytes: SnapshotGaugeVec::new(
+                opts!(
+                    "jemalloc_bytes",
+                    "Memory in bytes tracked by jemalloc per statistic"
+                )
+                .namespace("redis"),
+                &["stat", "target", "target_name"],
+                registry,
+            )?,
+            arena_pages: SnapshotGaugeVec::new(
+                opts!(
+                    "jemalloc_arena_pages",
+                    "Number of active, dirty and muzzy pages per jemalloc arena"
+                )
+                .namespace("redis"),
+                &["arena", "state", "target", "target_name"],
+                registry,
+            )?,
+            bin_allocated: SnapshotGaugeVec::new(
+                opts!(
+                    "jemalloc_bin_allocated_bytes",
+                    "Bytes allocated in the small size classes of a size group"
+                )
+                .namespace("redis"),
+                &["size_class", "target", "target_name"],
+                registry,
+            )?,
+            bin_regions: SnapshotGaugeVec::new(
+                opts!(
+                    "jemalloc_bin_regions",
+                    "Number of regions in use in the small size classes of a size group"
+                )
+                .namespace("redis"),
+                &["size_class", "target", "target_name"],
+                registry,
+            )?,
+            bin_slabs: SnapshotGaugeVec::new(
+                opts!(
+                    "jemalloc_bin_slabs",
+                    "Number of slabs in the small size classes of a size group"
+                )
+                .namespace("redis"),
+                &["size_class", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+
+    fn update(&self, target: &str, target_name: &str, stats: &MallocStats) {
+        let labels = |values: &[&str]| {
+            let mut labels: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            labels.extend([target.to_string(), target_name.to_string()]);
+            labels
+        };
+
+        self.bytes.set_target(
+            target,
+            stats
+                .bytes
+                .iter()
+                .map(|(stat, value)| (labels(&[stat]), *value))
+                .collect(),
+        );
+
+        let mut arena_pages = Vec::new();
+        for (arena, pages) in &stats.arenas {
+            arena_pages.push((labels(&[arena, "active"]), pages.active));
+            arena_pages.push((labels(&[arena, "dirty"]), pages.dirty));
+            arena_pages.push((labels(&[arena, "muzzy"]), pages.muzzy));
+        }
+        self.arena_pages.set_target(target, arena_pages);
+
+        let bins = |value: fn(&Bin) -> f64| {
+            stats
+                .bins
+                .iter()
+                .map(|(size_class, bin)| (labels(&[size_class]), value(bin)))
+                .collect()
+        };
+        self.bin_allocated.set_target(target, bins(|b| b.allocated));
+        self.bin_regions.set_target(target, bins(|b| b.regions));
+        self.bin_slabs.set_target(target, bins(|b| b.slabs));
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ArenaPages {
+    active: f64,
+    dirty: f64,
+    muzzy: f64,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Bin {
+    allocated: f64,
+    regions: f64,
+    slabs: f64,
+}
+
+#[derive(Debug, Default)]
+struct MallocStats {
+    bytes: Vec<(String, f64)>,
+    arenas: BTreeMap<String, ArenaPages>,
+    // Small size classes summed per power-of-two size group, from the merged
+    // arena stats only, so the label set stays the same whatever the arena count.
+    bins: BTreeMap<String, Bin>,
+}
+
+impl MallocStats {
+    // Parses the text of jemalloc's malloc_stats_print. Returns None when Redis
+    // is built with another allocator.
+    fn parse(text: &str) -> Option<Self> {
+        if !text.contains("jemalloc statistics") {
+            return None;
+        }
+
+        let mut stats = MallocStats::default();
+        let mut page_size = 4096f64;
+        let mut arena: Option<String> = None;
+        let mut bin_columns: Option<Vec<&str>> = None;
+        for line in text.lines() {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let Some(first) = tokens.first().copied() else {
+                continue;
+            };
+
+            if let Some(columns) = &bin_columns {
+                if first == "---" {
+                    continue;
+                }
+                if let Some((size, bin)) = parse_bin(columns, &tokens) {
+                    let group = stats.bins.entry(size_group(size)).or_default();
+                    group.allocated += bin.allocated;
+                    group.regions += bin.regions;
+                    group.slabs += bin.slabs;
+                    continue;
+                }
+                bin_columns = None;
+            }
+
+            if let Some(value) = line.strip_prefix("Page size:") {
+                page_size = value.trim().parse().unwrap_or(page_size);
+            } else if line.starts_with("Allocated:") {
+                stats.bytes = parse_global(line);
+            } else if line.starts_with("Merged arenas stats:") {
+                arena = Some("merged".to_string());
+            } else if let Some(index) = line
+                .strip_prefix("arenas[")
+                .and_then(|rest| rest.strip_suffix("]:"))
+            {
+                arena = Some(index.to_string());
+            } else if let Some(arena) = &arena {
+                let pages = stats.arenas.entry(arena.clone()).or_default();
+                let value = |i: usize| tokens.get(i).and_then(|v| v.parse::<f64>().ok());
+                match first {
+                    "dirty:" => pages.dirty = value(2).unwrap_or(0f64),
+                    "muzzy:" => pages.muzzy = value(2).unwrap_or(0f64),
+                    "active:" => pages.active = value(1).unwrap_or(0f64) / page_size,
+                    "bins:" if arena == "merged" => bin_columns = Some(tokens[1..].to_vec()),
+                    _ => {}
+                }
+            }
+        }
+        Some(stats)
+    }
+}
+
+// `Allocated: 1065504, active: 1273856, metadata: 2819424 (n_thp 0), ...`
+fn parse_global(line: &str) -> Vec<(String, f64)> {
+    line.split(',')
+        .filter_map(|field| {
+            let (name, value) = field.split_once(':')?;
+            let name = name.trim().to_lowercase();
+            let value = value.split_whitespace().next()?.parse::<f64>().ok()?;
+            GLOBAL_STATS
+                .contains(&name.as_str())
+                .then_some((name, value))
+        })
+        .collect()
+}
+
+// A row of the bins table, whose columns are named by its header. Rate
+// columns are headed `(#/sec)`, so rows and header have the same width.
+fn parse_bin(columns: &[&str], tokens: &[&str]) -> Option<(u64, Bin)> {
+    if columns.len() != tokens.len() {
+        return None;
+    }
+    let value = |name: &str| {
+        let i = columns.iter().position(|column| *column == name)?;
+        tokens[i].parse::<f64>().ok()
+    };
+
+    let size = tokens.first()?.parse::<u64>().ok()?;
+    Some((
+        size,
+        Bin {
+            allocated: value("allocated")?,
+            regions: value("curregs").unwrap_or(0f64),
+            slabs: value("curslabs").unwrap_or(0f64),
+        },
+    ))
+}
+
+// Labels a size class with its power-of-two group, e.g. 48 and 64 are `33-64`.
+fn size_group(size: u64) -> String {
+    let upper = size.next_power_of_two();
+    let lower = if upper <= 8 { 1 } else { upper / 2 + 1 };
+    format!("{}-{}", lower, upper)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MALLOC_STATS: &str = "___ Begin jemalloc statistics ___
+Version: \"5.3.0-0-g0\"
+Arenas: 4
+Quantum size: 8
+Page size: 4096
+Allocated: 1065504, active: 1273856, metadata: 2819424 (n_thp 0), resident: 3837952, mapped: 6451200, retained: 1523712
+Merged arenas stats:
+assigned threads: 1
+decaying:  time       npages       sweeps     madvises       purged
+   dirty:   N/A           31            0            0            0
+   muzzy:   N/A            2            0            0            0
+active:                       1273856
+mapped:                       6451200
+bins:           size ind    allocated      nmalloc (#/sec)      curregs     curslabs regs pgs   util
+                   8   0         2272          300     72          284            1  512   1  0.554
+                  48   5         4800          100     10          100            2  256   3  0.195
+                  64   6         6400          100     10          100            1   64   1  1
+                     ---
+large:          size ind    allocated      nmalloc (#/sec)      curlextents
+               16384  36        16384            1      0            1
+arenas[0]:
+decaying:  time       npages       sweeps     madvises       purged
+   dirty: 10000           31            0            0            0
+   muzzy:     0            2            0            0            0
+active:                       1273856
+bins:           size ind    allocated      nmalloc (#/sec)      curregs     curslabs regs pgs   util
+                   8   0         2272          300     72          284            1  512   1  0.554
+--- End jemalloc statistics ---
+";
+
+    #[test]
+    fn jemalloc_stats_are_parsed() {
+        assert!(MallocStats::parse("Stats not supported for the current allocator").is_none());
+
+        let stats = MallocStats::parse(MALLOC_STATS).unwrap();
+        assert_eq!(stats.bytes.len(), GLOBAL_STATS.len());
+        assert_eq!(stats.bytes[4], ("mapped".to_string(), 6451200f64));
+
+        assert_eq!(stats.arenas.len(), 2);
+        assert_eq!(
+            stats.arenas["merged"],
+            ArenaPages {
+                active: 311f64,
+                dirty: 31f64,
+                muzzy: 2f64,
+            }
+        );
+
+        assert_eq!(stats.bins.keys().collect::<Vec<_>>(), vec!["1-8", "33-64"]);
+        assert_eq!(
+            stats.bins["33-64"],
+            Bin {
+                allocated: 11200f64,
+                regions: 200f64,
+                slabs: 3f64,
+            }
+        );
+    }
+}
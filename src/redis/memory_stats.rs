@@ -0,0 +1,184 @@
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::debug;
+use prometheus::{opts, Registry};
+use redis::{RedisResult, Value};
+use std::sync::Arc;
+
+pub struct Collector {
+    target: Target,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>) -> Self {
+        Self { target, metrics }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "memory stats";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let stats: Value = redis::cmd("MEMORY")
+            .arg("STATS")
+            .query_async(&mut self.target.conn.get().await?)
+            .await?;
+
+        debug!("Receive data from MEMORY STATS command");
+        self.metrics
+            .update(&self.target.url, &self.target.name, &parse_stats(&stats));
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    stats: SnapshotGaugeVec,
+    db_stats: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            stats: SnapshotGaugeVec::new(
+                opts!("memory_stats", "Value of a MEMORY STATS field").namespace("redis"),
+                &["stat", "target", "target_name"],
+                registry,
+            )?,
+            db_stats: SnapshotGaugeVec::new(
+                opts!(
+                    "memory_stats_db",
+                    "Value of a per-database MEMORY STATS field"
+                )
+                .namespace("redis"),
+                &["db", "stat", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+
+    fn update(&self, target: &str, target_name: &str, stats: &[Stat]) {
+        let mut instance = Vec::new();
+        let mut databases = Vec::new();
+        for stat in stats {
+            match &stat.db {
+                Some(db) => databases.push((
+                    vec![
+                        db.clone(),
+                        stat.name.clone(),
+                        target.to_string(),
+                        target_name.to_string(),
+                    ],
+                    stat.value,
+                )),
+                None => instance.push((
+                    vec![
+                        stat.name.clone(),
+                        target.to_string(),
+                        target_name.to_string(),
+                    ],
+                    stat.value,
+                )),
+            }
+        }
+        self.stats.set_target(target, instance);
+        self.db_stats.set_target(target, databases);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Stat {
+    db: Option<String>,
+    name: String,
+    value: f64,
+}
+
+// Flattens the reply into numeric fields. Databases are nested under `db.<N>`
+// keys, and fields that are not numbers (e.g. `allocator-stats`) are skipped.
+fn parse_stats(reply: &Value) -> Vec<Stat> {
+    let mut stats = Vec::new();
+    for (key, value) in reply::as_pairs(reply) {
+        let Some(key) = reply::as_string(key) else {
+            continue;
+        };
+
+        if let Some(db) = key.strip_prefix("db.") {
+            for (name, value) in reply::as_pairs(value) {
+                if let (Some(name), Some(value)) = (reply::as_string(name), reply::as_f64(value)) {
+                    stats.push(Stat {
+                        db: Some(db.to_string()),
+                        name,
+                        value,
+                    });
+                }
+            }
+        } else if let Some(value) = reply::as_f64(value) {
+            stats.push(Stat {
+                db: None,
+                name: key,
+                value,
+            });
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(s: &str) -> Value {
+        Value::BulkString(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn nested_databases_are_flattened() {
+        let reply = Value::Array(vec![
+            bulk("peak.allocated"),
+            Value::Int(1_048_576),
+            bulk("db.0"),
+            Value::Array(vec![
+                bulk("overhead.hashtable.main"),
+                Value::Int(72),
+                bulk("overhead.hashtable.expires"),
+                Value::Int(32),
+            ]),
+            bulk("dataset.percentage"),
+            bulk("42.5"),
+            bulk("allocator-stats"),
+            bulk("___ Begin jemalloc statistics ___"),
+        ]);
+
+        assert_eq!(
+            parse_stats(&reply),
+            vec![
+                Stat {
+                    db: None,
+                    name: "peak.allocated".to_string(),
+                    value: 1_048_576f64,
+                },
+                Stat {
+                    db: Some("0".to_string()),
+                    name: "overhead.hashtable.main".to_string(),
+                    value: 72f64,
+                },
+                Stat {
+                    db: Some("0".to_string()),
+                    name: "overhead.hashtable.expires".to_string(),
+                    value: 32f64,
+                },
+                Stat {
+                    db: None,
+                    name: "dataset.percentage".to_string(),
+                    value: 42.5,
+                },
+            ]
+        );
+    }
+}
@@ -1,394 +1,469 @@
-use log::{debug, info};
+use crate::prometheus::series::SeriesTracker;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::connection;
+use log::{debug, info, warn};
 use prometheus::{
-    opts, register_counter, register_counter_vec, register_gauge_vec, CounterVec, GaugeVec,
+    opts, register_counter_vec_with_registry, register_gauge_vec_with_registry, Counter,
+    CounterVec, GaugeVec, Registry,
 };
-use redis::aio::MultiplexedConnection;
-use redis::RedisResult;
-use std::collections::HashMap;
+use redis::{ErrorKind, RedisError, RedisResult};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub struct Collector {
-    conn: MultiplexedConnection,
-    target: String,
-    target_name: String,
+    target: Target,
+    latency_percentiles: Option<Vec<f64>>,
+    zero_fill_databases: bool,
+    databases: Mutex<Option<u64>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>, config: &connection::Config) -> Self {
+        Self {
+            target,
+            latency_percentiles: config.latency_percentiles.clone(),
+            zero_fill_databases: config.zero_fill_databases,
+            databases: Mutex::new(None),
+            metrics,
+        }
+    }
+
+    async fn databases(&self) -> Option<u64> {
+        if let Some(databases) = *self.databases.lock().unwrap() {
+            return Some(databases);
+        }
+
+        let result: RedisResult<HashMap<String, String>> = match self.target.conn.get().await {
+            Ok(mut conn) => {
+                redis::cmd("CONFIG")
+                    .arg("GET")
+                    .arg("databases")
+                    .query_async(&mut conn)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let databases = match result {
+            Ok(config) => config.get("databases").and_then(|v| v.parse::<u64>().ok()),
+            Err(e) => {
+                warn!(
+                    "Unable to get number of databases for target {}: {}",
+                    &self.target.name, e
+                );
+                None
+            }
+        };
+        *self.databases.lock().unwrap() = databases;
+        databases
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "info";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let start = Instant::now();
+        // A target unreachable at startup is reported like a failed scrape
+        let result: RedisResult<String> = match self.target.conn.get().await {
+            Ok(mut conn) => redis::cmd("INFO").arg("ALL").query_async(&mut conn).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(info) => {
+                debug!("Receive data from INFO command");
+                let databases = match self.zero_fill_databases {
+                    true => self.databases().await,
+                    false => None,
+                };
+                self.metrics.update(
+                    &self.target.url,
+                    &self.target.name,
+                    self.latency_percentiles.as_deref(),
+                    databases,
+                    &info,
+                );
+                self.metrics.record_scrape(
+                    &self.target.url,
+                    &self.target.name,
+                    start.elapsed(),
+                    None,
+                );
+
+                debug!("Metrics collected");
+                Ok(())
+            }
+            Err(e) => {
+                self.metrics.record_scrape(
+                    &self.target.url,
+                    &self.target.name,
+                    start.elapsed(),
+                    Some(&e),
+                );
+                Err(e)
+            }
+        }
+    }
+}
+
+// String fields of INFO exported as labels of `redis_instance_info`.
+const INSTANCE_INFO_FIELDS: [&str; 8] = [
+    "redis_version",
+    "redis_build_id",
+    "redis_mode",
+    "role",
+    "os",
+    "tcp_port",
+    "run_id",
+    "maxmemory_policy",
+];
+
+// A `slaveN:ip=...,port=...,state=...,offset=...,lag=...` line of the
+// replication section.
+struct Replica<'a> {
+    ip: &'a str,
+    port: &'a str,
+    state: &'a str,
+    offset: Option<f64>,
+    lag: Option<f64>,
+}
+
+impl<'a> Replica<'a> {
+    fn parse(field: &str, value: &'a str) -> Option<Self> {
+        let index = field.strip_prefix("slave")?;
+        if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let stats_map: HashMap<&str, &str> =
+            value.split(',').filter_map(|s| s.split_once('=')).collect();
+        Some(Self {
+            ip: stats_map.get("ip")?,
+            port: stats_map.get("port")?,
+            state: stats_map.get("state").copied().unwrap_or("unknown"),
+            offset: stats_map.get("offset").and_then(|v| v.parse().ok()),
+            lag: stats_map.get("lag").and_then(|v| v.parse().ok()),
+        })
+    }
+}
+
+// Counters mirror the absolute value reported by Redis. When the server restarted
+// (new run_id, or a value lower than the current one) the counter starts over.
+fn set_counter(counter: &Counter, value: f64, restarted: bool) {
+    let current = counter.get();
+    if restarted || value < current {
+        counter.reset();
+        counter.inc_by(value);
+    } else {
+        counter.inc_by(value - current);
+    }
+}
 
+fn error_kind(err: &RedisError) -> &'static str {
+    if err.is_timeout() {
+        "timeout"
+    } else if err.is_connection_refusal() || err.is_connection_dropped() || err.is_io_error() {
+        "connection"
+    } else if err.kind() == ErrorKind::AuthenticationFailed {
+        "auth"
+    } else {
+        "command"
+    }
+}
+
+pub struct Metrics {
     up: GaugeVec,
-    uptime: GaugeVec,
-    process_id: GaugeVec,
-    io_threads_active: GaugeVec,
-    connected_clients: GaugeVec,
-    blocked_clients: GaugeVec,
-    tracking_clients: GaugeVec,
-    clients_in_timeout_table: GaugeVec,
-    pubsub_clients: GaugeVec,
-    watching_clients: GaugeVec,
-    total_watched_keys: GaugeVec,
-    total_blocking_keys: GaugeVec,
-    total_blocking_keys_on_nokey: GaugeVec,
-    client_longest_output_list: GaugeVec,
-    client_biggest_input_buf: GaugeVec,
-    client_recent_max_output_buffer: GaugeVec,
-    client_recent_max_input_buffer: GaugeVec,
-    allocator_active: GaugeVec,
-    allocator_allocated: GaugeVec,
-    allocator_resident: GaugeVec,
-    allocator_frag_ratio: GaugeVec,
-    allocator_frag_bytes: GaugeVec,
-    allocator_rss_ratio: GaugeVec,
-    allocator_rss_bytes: GaugeVec,
-    used_memory: GaugeVec,
-    used_memory_rss: GaugeVec,
-    used_memory_peak: GaugeVec,
-    used_memory_lua: GaugeVec,
-    used_memory_vm_eval: GaugeVec,
-    used_memory_scripts_eval: GaugeVec,
-    used_memory_overhead: GaugeVec,
-    used_memory_startup: GaugeVec,
-    used_memory_dataset: GaugeVec,
-    number_of_cached_scripts: GaugeVec,
-    number_of_functions: GaugeVec,
-    number_of_libraries: GaugeVec,
-    used_memory_vm_functions: GaugeVec,
-    used_memory_scripts: GaugeVec,
-    used_memory_functions: GaugeVec,
-    used_memory_vm_total: GaugeVec,
-    maxmemory: GaugeVec,
-    memory_fragmentation_ratio: GaugeVec,
-    connected_slaves: GaugeVec,
-    keyspace_hits: GaugeVec,
-    keyspace_misses: GaugeVec,
-    commands_total: GaugeVec,
-    commands_rejected_total: GaugeVec,
-    commands_duration_total: GaugeVec,
+    info: HashMap<&'static str, (&'static MetricSpec, InfoMetric)>,
+
+    commands_total: CounterVec,
+    commands_rejected_total: CounterVec,
+    commands_failed_total: CounterVec,
+    commands_duration_total: CounterVec,
+    errors_total: CounterVec,
+    latency_percentiles: GaugeVec,
     db_keys: GaugeVec,
     db_expiring_keys: GaugeVec,
-    master_last_io_seconds_ago: GaugeVec,
+    db_avg_ttl: GaugeVec,
+    db_subexpiry_keys: GaugeVec,
+    connected_slave_offset: GaugeVec,
+    connected_slave_lag: GaugeVec,
+    connected_slave_repl_lag: GaugeVec,
+    connected_slave_state: GaugeVec,
+    instance_info: GaugeVec,
+
+    last_scrape_success: GaugeVec,
+    last_scrape_timestamp: GaugeVec,
+    scrape_duration: GaugeVec,
+    scrape_errors_total: CounterVec,
 
-    evicted_keys: CounterVec,
+    run_ids: Mutex<HashMap<String, String>>,
+    replica_series: SeriesTracker,
+    replica_state_series: SeriesTracker,
+    instance_info_series: SeriesTracker,
+    latency_percentiles_series: SeriesTracker,
+    keyspace_series: SeriesTracker,
 }
 
-impl Collector {
-    pub fn new(
-        conn: MultiplexedConnection,
-        target: &str,
-        target_name: &str,
-    ) -> Result<Self, prometheus::Error> {
+enum InfoMetric {
+    Gauge(GaugeVec),
+    Counter(CounterVec),
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let mut info = HashMap::with_capacity(INFO_METRICS.len());
+        for spec in INFO_METRICS {
+            let opts = opts!(spec.metric_name(), spec.help).namespace("redis");
+            let metric = match spec.metric_type {
+                MetricType::Gauge => InfoMetric::Gauge(register_gauge_vec_with_registry!(
+                    opts,
+                    &["target", "target_name"],
+                    registry
+                )?),
+                MetricType::Counter => InfoMetric::Counter(register_counter_vec_with_registry!(
+                    opts,
+                    &["target", "target_name"],
+                    registry
+                )?),
+            };
+            info.insert(spec.field, (spec, metric));
+        }
+
         Ok(Self {
-            conn,
-            target: target.to_string(),
-            target_name: target_name.to_string(),
-            up: register_gauge_vec!(
+            up: register_gauge_vec_with_registry!(
                 opts!("up", "Target is online").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            uptime: register_gauge_vec!(
-                opts!("uptime_in_seconds", "Target uptime in seconds").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            process_id: register_gauge_vec!(
-                opts!("process_id", "Redis process ID").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            io_threads_active: register_gauge_vec!(
-                opts!("io_threads_active", "Number of IO threads active").namespace("redis"),
-                &["target", "target_name"]
+                &["target", "target_name"],
+                registry
             )?,
-            connected_clients: register_gauge_vec!(
-                opts!("connected_clients", "Number of connected clients").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            blocked_clients: register_gauge_vec!(
-                opts!("blocked_clients", "Number of blocked clients").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            tracking_clients: register_gauge_vec!(
-                opts!("tracking_clients", "Number of tracking clients").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            clients_in_timeout_table: register_gauge_vec!(
-                opts!(
-                    "clients_in_timeout_table",
-                    "Number of client in-memory time out"
-                )
-                .namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            pubsub_clients: register_gauge_vec!(
-                opts!("pubsub_clients", "Number of pubsub clients").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            watching_clients: register_gauge_vec!(
-                opts!("watching_clients", "Number of watching clients").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            total_watched_keys: register_gauge_vec!(
-                opts!("total_watched_keys", "Number of total watching keys").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            total_blocking_keys: register_gauge_vec!(
-                opts!("total_blocking_keys", "Number of blocking keys").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            total_blocking_keys_on_nokey: register_gauge_vec!(
-                opts!(
-                    "total_blocking_keys_on_nokey",
-                    "Number of blocking keys on nokey"
-                )
-                .namespace("redis"),
-                &["target", "target_name"]
+            info,
+
+            commands_total: register_counter_vec_with_registry!(
+                opts!("commands_total", "Total number of calls per command").namespace("redis"),
+                &["cmd", "target", "target_name"],
+                registry
             )?,
-            client_longest_output_list: register_gauge_vec!(
+            commands_rejected_total: register_counter_vec_with_registry!(
                 opts!(
-                    "client_longest_output_list",
-                    "Longest output list among clients"
+                    "commands_rejected_calls_total",
+                    "Total number of errors within command execution per command"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["cmd", "target", "target_name"],
+                registry
             )?,
-            client_biggest_input_buf: register_gauge_vec!(
+            commands_failed_total: register_counter_vec_with_registry!(
                 opts!(
-                    "client_biggest_input_buf",
-                    "Biggest input buffer among clients"
+                    "commands_failed_calls_total",
+                    "Total number of failed calls within command execution per command"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["cmd", "target", "target_name"],
+                registry
             )?,
-            client_recent_max_output_buffer: register_gauge_vec!(
+            commands_duration_total: register_counter_vec_with_registry!(
                 opts!(
-                    "client_recent_max_output_buffer",
-                    "Recent maximum output buffer among clients"
+                    "commands_duration_seconds_total",
+                    "Total amount of time in seconds spent per command"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["cmd", "target", "target_name"],
+                registry
             )?,
-            client_recent_max_input_buffer: register_gauge_vec!(
+            errors_total: register_counter_vec_with_registry!(
                 opts!(
-                    "client_recent_max_input_buffer",
-                    "Recent maximum input buffer among clients"
+                    "errors_total",
+                    "Total number of error replies per error prefix"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["err", "target", "target_name"],
+                registry
             )?,
-            allocator_active: register_gauge_vec!(
-                opts!("allocator_active", "Total size of allocated memory").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            allocator_allocated: register_gauge_vec!(
+            latency_percentiles: register_gauge_vec_with_registry!(
                 opts!(
-                    "allocator_allocated",
-                    "Total size of allocated memory including internal fragmentation"
+                    "latency_percentiles_usec",
+                    "Latency percentiles per command in microseconds"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            allocator_resident: register_gauge_vec!(
-                opts!("allocator_resident", "Total size of resident memory").namespace("redis"),
-                &["target", "target_name"]
+                &["cmd", "quantile", "target", "target_name"],
+                registry
             )?,
-            allocator_frag_ratio: register_gauge_vec!(
-                opts!("allocator_frag_ratio", "Ratio of memory fragmentation").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            allocator_frag_bytes: register_gauge_vec!(
-                opts!(
-                    "allocator_frag_bytes",
-                    "Amount of memory fragmentation in bytes"
-                )
-                .namespace("redis"),
-                &["target", "target_name"]
+            db_keys: register_gauge_vec_with_registry!(
+                opts!("db_keys", "Total number of keys per DB").namespace("redis"),
+                &["db", "target", "target_name"],
+                registry
             )?,
-            allocator_rss_ratio: register_gauge_vec!(
-                opts!(
-                    "allocator_rss_ratio",
-                    "Ratio of resident set size to allocated memory"
-                )
-                .namespace("redis"),
-                &["target", "target_name"]
+            db_expiring_keys: register_gauge_vec_with_registry!(
+                opts!("db_keys_expiring", "Total number of expiring keys by DB").namespace("redis"),
+                &["db", "target", "target_name"],
+                registry
             )?,
-            allocator_rss_bytes: register_gauge_vec!(
-                opts!("allocator_rss_bytes", "Number of resident set size bytes")
+            db_avg_ttl: register_gauge_vec_with_registry!(
+                opts!("db_avg_ttl_seconds", "Average TTL of expiring keys by DB")
                     .namespace("redis"),
-                &["target", "target_name"]
+                &["db", "target", "target_name"],
+                registry
             )?,
-            used_memory: register_gauge_vec!(
+            db_subexpiry_keys: register_gauge_vec_with_registry!(
                 opts!(
-                    "memory_used_bytes",
-                    "Total number of bytes allocated by Redis"
+                    "db_keys_subexpiry",
+                    "Total number of keys with expiring hash fields by DB"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["db", "target", "target_name"],
+                registry
             )?,
-            used_memory_rss: register_gauge_vec!(
+            connected_slave_offset: register_gauge_vec_with_registry!(
                 opts!(
-                    "memory_used_rss_bytes",
-                    "Number of bytes that Redis allocated as seen by the operating system"
+                    "connected_slave_offset_bytes",
+                    "Replication offset acknowledged by the replica"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["slave_ip", "slave_port", "target", "target_name"],
+                registry
             )?,
-            used_memory_peak: register_gauge_vec!(
+            connected_slave_lag: register_gauge_vec_with_registry!(
                 opts!(
-                    "memory_used_peak_bytes",
-                    "Peak memory consumed by Redis (in bytes)"
+                    "connected_slave_lag_seconds",
+                    "Seconds since the last acknowledgement from the replica"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["slave_ip", "slave_port", "target", "target_name"],
+                registry
             )?,
-            used_memory_lua: register_gauge_vec!(
+            connected_slave_repl_lag: register_gauge_vec_with_registry!(
                 opts!(
-                    "memory_used_lua_bytes",
-                    "Number of bytes used by the Lua engine"
+                    "connected_slave_replication_lag_bytes",
+                    "Difference between the master offset and the replica offset"
                 )
                 .namespace("redis"),
-                &["target", "target_name"]
+                &["slave_ip", "slave_port", "target", "target_name"],
+                registry
             )?,
-            used_memory_vm_eval: register_gauge_vec!(
-                opts!(
-                    "memory_used_vm_eval_bytes",
-                    "Memory used by VM for evaluation"
-                )
-                .namespace("redis"),
-                &["target", "target_name"]
+            connected_slave_state: register_gauge_vec_with_registry!(
+                opts!("connected_slave_state", "Replication state of the replica")
+                    .namespace("redis"),
+                &["slave_ip", "slave_port", "state", "target", "target_name"],
+                registry
             )?,
-            used_memory_scripts_eval: register_gauge_vec!(
-                opts!(
-                    "memory_used_scripts_eval_bytes",
-                    "Memory used for script evaluation"
-                )
-                .namespace("redis"),
-                &["target", "target_name"]
+            instance_info: register_gauge_vec_with_registry!(
+                opts!("instance_info", "Information about the Redis instance").namespace("redis"),
+                &[
+                    "redis_version",
+                    "redis_build_id",
+                    "redis_mode",
+                    "role",
+                    "os",
+                    "tcp_port",
+                    "run_id",
+                    "maxmemory_policy",
+                    "target",
+                    "target_name"
+                ],
+                registry
             )?,
-            used_memory_overhead: register_gauge_vec!(
+
+            last_scrape_success: register_gauge_vec_with_registry!(
                 opts!(
-                    "memory_used_overhead_bytes",
-                    "The sum in bytes of all overheads allocated by Redis"
+                    "last_scrape_success",
+                    "Whether the last scrape of the target succeeded"
                 )
-                .namespace("redis"),
-                &["target", "target_name"]
+                .namespace("redis_exporter"),
+                &["target", "target_name"],
+                registry
             )?,
-            used_memory_startup: register_gauge_vec!(
+            last_scrape_timestamp: register_gauge_vec_with_registry!(
                 opts!(
-                    "memory_used_startup_bytes",
-                    "Initial amount of memory consumed by Redis at startup"
+                    "last_scrape_timestamp_seconds",
+                    "Unix timestamp of the last scrape attempt"
                 )
-                .namespace("redis"),
-                &["target", "target_name"]
+                .namespace("redis_exporter"),
+                &["target", "target_name"],
+                registry
             )?,
-            used_memory_dataset: register_gauge_vec!(
+            scrape_duration: register_gauge_vec_with_registry!(
                 opts!(
-                    "memory_used_dataset_bytes",
-                    "The size in bytes of the dataset"
+                    "scrape_duration_seconds",
+                    "Duration of the last scrape in seconds"
                 )
-                .namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            number_of_cached_scripts: register_gauge_vec!(
-                opts!("number_of_cached_scripts", "Number of cached scripts").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            number_of_functions: register_gauge_vec!(
-                opts!("number_of_functions", "Number of functions").namespace("redis"),
-                &["target", "target_name"]
+                .namespace("redis_exporter"),
+                &["target", "target_name"],
+                registry
             )?,
-            number_of_libraries: register_gauge_vec!(
-                opts!("number_of_libraries", "Number of libraries").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            used_memory_vm_functions: register_gauge_vec!(
+            scrape_errors_total: register_counter_vec_with_registry!(
                 opts!(
-                    "memory_used_vm_functions_bytes",
-                    "Memory used by VM functions"
+                    "scrape_errors_total",
+                    "Total number of failed scrapes by kind"
                 )
-                .namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            used_memory_scripts: register_gauge_vec!(
-                opts!("memory_used_scripts_bytes", "Memory used by scripts").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            used_memory_functions: register_gauge_vec!(
-                opts!("memory_used_functions_bytes", "Memory used by functions").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            used_memory_vm_total: register_gauge_vec!(
-                opts!("used_memory_vm_total", "Total memory used by VM").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            maxmemory: register_gauge_vec!(
-                opts!("memory_max_bytes", "Maximum amount of memory Redis can use")
-                    .namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            memory_fragmentation_ratio: register_gauge_vec!(
-                opts!("memory_fragmentation_ratio", "Memory fragmentation ratio")
-                    .namespace("redis"),
-                &["target", "target_name"]
+                .namespace("redis_exporter"),
+                &["kind", "target", "target_name"],
+                registry
             )?,
 
-            keyspace_hits: register_gauge_vec!(
-                opts!("keyspace_hits_total", "Total number of keyspace hits").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            keyspace_misses: register_gauge_vec!(
-                opts!("keyspace_misses_total", "Total number of keyspace misses")
-                    .namespace("redis"),
-                &["target", "target_name"]
-            )?,
+            run_ids: Mutex::new(HashMap::new()),
+            replica_series: SeriesTracker::new(),
+            replica_state_series: SeriesTracker::new(),
+            instance_info_series: SeriesTracker::new(),
+            latency_percentiles_series: SeriesTracker::new(),
+            keyspace_series: SeriesTracker::new(),
+        })
+    }
 
-            commands_total: register_gauge_vec!(
-                opts!("commands_total", "Total number of calls per command").namespace("redis"),
-                &["cmd", "target", "target_name"]
-            )?,
-            commands_rejected_total: register_gauge_vec!(
-                opts!(
-                    "commands_rejected_calls_total",
-                    "Total number of errors within command execution per command"
-                )
-                .namespace("redis"),
-                &["cmd", "target", "target_name"]
-            )?,
-            commands_duration_total: register_gauge_vec!(
-                opts!(
-                    "commands_duration_seconds_total",
-                    "Total amount of time in seconds spent per command"
-                )
-                .namespace("redis"),
-                &["cmd", "target", "target_name"]
-            )?,
-            db_keys: register_gauge_vec!(
-                opts!("db_keys", "Total number of keys per DB").namespace("redis"),
-                &["db", "target", "target_name"]
-            )?,
-            db_expiring_keys: register_gauge_vec!(
-                opts!("db_keys_expiring", "Total number of expiring keys by DB").namespace("redis"),
-                &["db", "target", "target_name"]
-            )?,
-            master_last_io_seconds_ago: register_gauge_vec!(
-                opts!("master_last_io_seconds_ago", "Master last io seconds ago")
-                    .namespace("redis"),
-                &["target", "target_name"]
-            )?,
-            connected_slaves: register_gauge_vec!(
-                opts!("connected_slaves", "Number of connected slaves").namespace("redis"),
-                &["target", "target_name"]
-            )?,
+    pub fn record_scrape(
+        &self,
+        target: &str,
+        target_name: &str,
+        duration: Duration,
+        error: Option<&RedisError>,
+    ) {
+        let labels = [target, target_name];
+        match error {
+            Some(e) => {
+                self.up.with_label_values(&labels).set(0f64);
+                self.last_scrape_success
+                    .with_label_values(&labels)
+                    .set(0f64);
+                self.scrape_errors_total
+                    .with_label_values(&[error_kind(e), target, target_name])
+                    .inc();
+            }
+            None => self
+                .last_scrape_success
+                .with_label_values(&labels)
+                .set(1f64),
+        }
 
-            evicted_keys: register_counter_vec!(
-                opts!("evicted_keys_total", "Total number of evicted keys").namespace("redis"),
-                &["target", "target_name"]
-            )?,
-        })
+        self.scrape_duration
+            .with_label_values(&labels)
+            .set(duration.as_secs_f64());
+        if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            self.last_scrape_timestamp
+                .with_label_values(&labels)
+                .set(now.as_secs_f64());
+        }
     }
 
-    pub async fn collect(&self) -> RedisResult<()> {
-        let info: String = redis::cmd("INFO")
-            .arg("ALL")
-            .query_async(&mut self.conn.clone())
-            .await?;
+    pub fn update(
+        &self,
+        target: &str,
+        target_name: &str,
+        latency_percentiles: Option<&[f64]>,
+        databases: Option<u64>,
+        info: &str,
+    ) {
+        let restarted = self.detect_restart(target, info);
+        self.up.with_label_values(&[target, target_name]).set(1f64);
 
-        debug!("Receive data from INFO command");
-        self.up
-            .with_label_values(&[&self.target, &self.target_name])
-            .set(1f64);
+        let mut master_repl_offset = None;
+        let mut replicas = Vec::new();
+        let mut instance_info = [""; INSTANCE_INFO_FIELDS.len()];
+        let mut keyspace = Vec::new();
+        let mut latency_series = HashSet::new();
         for line in info.lines() {
             if line.is_empty() || line.starts_with('#') {
                 continue;
@@ -396,58 +471,260 @@ impl Collector {
 
             if let Some((metric, value)) = line.split_once(':') {
                 if metric.starts_with("cmdstat_") {
-                    self.add_cmdstat(metric, value);
+                    self.add_cmdstat(target, target_name, metric, value, restarted);
                     continue;
                 }
 
-                if let Some(metric) = self.parse_gauge_metric_name(metric) {
-                    let value = value.parse::<f64>().unwrap_or(0f64);
-                    metric
-                        .with_label_values(&[&self.target, &self.target_name])
-                        .set(value);
+                if let Some(cmd) = metric.strip_prefix("latency_percentiles_usec_") {
+                    self.add_latency_percentiles(
+                        target,
+                        target_name,
+                        cmd,
+                        value,
+                        latency_percentiles,
+                        &mut latency_series,
+                    );
                     continue;
                 }
 
-                if let Some(metric) = self.parse_counter_metric_name(metric) {
-                    let value = value.parse::<f64>().unwrap_or(0f64);
-                    metric
-                        .with_label_values(&[&self.target, &self.target_name])
-                        .inc_by(value);
+                if let Some(err) = metric.strip_prefix("errorstat_") {
+                    let count = value
+                        .strip_prefix("count=")
+                        .and_then(|count| count.parse::<f64>().ok());
+                    if let Some(count) = count {
+                        set_counter(
+                            &self
+                                .errors_total
+                                .with_label_values(&[err, target, target_name]),
+                            count,
+                            restarted,
+                        );
+                    }
                     continue;
                 }
 
-                if line.starts_with("db") {
-                    let (db, stats) = line.split_once(':').unwrap();
-                    let stats_map: HashMap<&str, &str> =
-                        stats.split(',').filter_map(|s| s.split_once('=')).collect();
+                if let Some(index) = INSTANCE_INFO_FIELDS.iter().position(|f| *f == metric) {
+                    instance_info[index] = value;
+                    continue;
+                }
 
-                    if let Some(keys) = stats_map.get("keys") {
-                        if let Ok(value) = keys.parse::<f64>() {
-                            self.db_keys
-                                .with_label_values(&[db, &self.target, &self.target_name])
-                                .set(value);
-                        }
-                    }
+                if let Some(replica) = Replica::parse(metric, value) {
+                    replicas.push(replica);
+                    continue;
+                }
+
+                if metric == "master_repl_offset" {
+                    master_repl_offset = value.parse::<f64>().ok();
+                }
 
-                    if let Some(keys) = stats_map.get("expires") {
-                        if let Ok(value) = keys.parse::<f64>() {
-                            self.db_expiring_keys
-                                .with_label_values(&[db, &self.target, &self.target_name])
-                                .set(value);
+                if let Some((spec, family)) = self.info.get(metric) {
+                    let Some(value) = spec.parse(value) else {
+                        debug!("Unable to parse value {} of metric {}", value, metric);
+                        continue;
+                    };
+                    match family {
+                        InfoMetric::Gauge(gauge) => {
+                            gauge.with_label_values(&[target, target_name]).set(value)
                         }
+                        InfoMetric::Counter(counter) => set_counter(
+                            &counter.with_label_values(&[target, target_name]),
+                            value,
+                            restarted,
+                        ),
                     }
                     continue;
                 }
 
+                if let Some(db) = metric
+                    .strip_prefix("db")
+                    .and_then(|db| db.parse::<u64>().ok())
+                {
+                    let stats_map: HashMap<&str, &str> =
+                        value.split(',').filter_map(|s| s.split_once('=')).collect();
+                    keyspace.push((db, stats_map));
+                    continue;
+                }
+
                 debug!("Metric {} not found", metric);
             }
         }
 
-        debug!("Metrics collected");
-        Ok(())
+        self.update_replicas(target, target_name, master_repl_offset, &replicas);
+        self.update_keyspace(target, target_name, databases, &keyspace);
+        // Commands missing from the reply, e.g. after CONFIG RESETSTAT, are removed
+        self.latency_percentiles_series.retain(
+            target,
+            latency_series,
+            &[&self.latency_percentiles],
+        );
+
+        let mut labels: Vec<&str> = instance_info.to_vec();
+        labels.extend([target, target_name]);
+        self.instance_info.with_label_values(&labels).set(1f64);
+        self.instance_info_series.retain(
+            target,
+            HashSet::from([labels.into_iter().map(String::from).collect()]),
+            &[&self.instance_info],
+        );
     }
 
-    fn add_cmdstat(&self, metric: &str, value: &str) {
+    fn update_keyspace(
+        &self,
+        target: &str,
+        target_name: &str,
+        databases: Option<u64>,
+        keyspace: &[(u64, HashMap<&str, &str>)],
+    ) {
+        let mut series = HashSet::new();
+        for (db, stats_map) in keyspace {
+            let db = db.to_string();
+            let labels = [db.as_str(), target, target_name];
+            let stat = |name: &str| stats_map.get(name).and_then(|v| v.parse::<f64>().ok());
+
+            if let Some(keys) = stat("keys") {
+                self.db_keys.with_label_values(&labels).set(keys);
+            }
+            if let Some(expires) = stat("expires") {
+                self.db_expiring_keys
+                    .with_label_values(&labels)
+                    .set(expires);
+            }
+            if let Some(avg_ttl) = stat("avg_ttl") {
+                self.db_avg_ttl
+                    .with_label_values(&labels)
+                    .set(avg_ttl / 1000.0);
+            }
+            if let Some(subexpiry) = stat("subexpiry") {
+                self.db_subexpiry_keys
+                    .with_label_values(&labels)
+                    .set(subexpiry);
+            }
+            series.insert(labels.map(String::from).to_vec());
+        }
+
+        let reported: HashSet<u64> = keyspace.iter().map(|(db, _)| *db).collect();
+        for db in (0..databases.unwrap_or(0)).filter(|db| !reported.contains(db)) {
+            let db = db.to_string();
+            let labels = [db.as_str(), target, target_name];
+            self.db_keys.with_label_values(&labels).set(0f64);
+            self.db_expiring_keys.with_label_values(&labels).set(0f64);
+            self.db_avg_ttl.with_label_values(&labels).set(0f64);
+            self.db_subexpiry_keys.with_label_values(&labels).set(0f64);
+            series.insert(labels.map(String::from).to_vec());
+        }
+
+        self.keyspace_series.retain(
+            target,
+            series,
+            &[
+                &self.db_keys,
+                &self.db_expiring_keys,
+                &self.db_avg_ttl,
+                &self.db_subexpiry_keys,
+            ],
+        );
+    }
+
+    fn update_replicas(
+        &self,
+        target: &str,
+        target_name: &str,
+        master_repl_offset: Option<f64>,
+        replicas: &[Replica],
+    ) {
+        let mut series = HashSet::new();
+        let mut state_series = HashSet::new();
+        for replica in replicas {
+            let labels = [replica.ip, replica.port, target, target_name];
+            if let Some(offset) = replica.offset {
+                self.connected_slave_offset
+                    .with_label_values(&labels)
+                    .set(offset);
+                if let Some(master_offset) = master_repl_offset {
+                    self.connected_slave_repl_lag
+                        .with_label_values(&labels)
+                        .set((master_offset - offset).max(0f64));
+                }
+            }
+            if let Some(lag) = replica.lag {
+                self.connected_slave_lag.with_label_values(&labels).set(lag);
+            }
+            series.insert(labels.map(String::from).to_vec());
+
+            let state_labels = [replica.ip, replica.port, replica.state, target, target_name];
+            self.connected_slave_state
+                .with_label_values(&state_labels)
+                .set(1f64);
+            state_series.insert(state_labels.map(String::from).to_vec());
+        }
+
+        self.replica_series.retain(
+            target,
+            series,
+            &[
+                &self.connected_slave_offset,
+                &self.connected_slave_lag,
+                &self.connected_slave_repl_lag,
+            ],
+        );
+        self.replica_state_series
+            .retain(target, state_series, &[&self.connected_slave_state]);
+    }
+
+    fn detect_restart(&self, target: &str, info: &str) -> bool {
+        let Some(run_id) = info.lines().find_map(|line| line.strip_prefix("run_id:")) else {
+            return false;
+        };
+
+        let mut run_ids = self.run_ids.lock().unwrap();
+        match run_ids.insert(target.to_string(), run_id.to_string()) {
+            Some(previous) if previous != run_id => {
+                info!("Target {} restarted, resetting counters", target);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn add_latency_percentiles(
+        &self,
+        target: &str,
+        target_name: &str,
+        cmd: &str,
+        value: &str,
+        percentiles: Option<&[f64]>,
+        series: &mut HashSet<Vec<String>>,
+    ) {
+        for (percentile, latency) in value.split(',').filter_map(|s| s.split_once('=')) {
+            let (Some(percentile), Ok(latency)) = (
+                percentile
+                    .strip_prefix('p')
+                    .and_then(|p| p.parse::<f64>().ok()),
+                latency.parse::<f64>(),
+            ) else {
+                continue;
+            };
+            if percentiles.is_some_and(|percentiles| !percentiles.contains(&percentile)) {
+                continue;
+            }
+
+            let quantile = ((percentile / 100.0) * 1e6).round() / 1e6;
+            let labels = [cmd, &quantile.to_string(), target, target_name].map(String::from);
+            self.latency_percentiles
+                .with_label_values(&labels.each_ref().map(String::as_str))
+                .set(latency);
+            series.insert(labels.to_vec());
+        }
+    }
+
+    fn add_cmdstat(
+        &self,
+        target: &str,
+        target_name: &str,
+        metric: &str,
+        value: &str,
+        restarted: bool,
+    ) {
         let cmd = metric.strip_prefix("cmdstat_").unwrap();
 
         let stats_map: HashMap<&str, &str> =
@@ -455,86 +732,828 @@ impl Collector {
 
         if let Some(calls) = stats_map.get("calls") {
             if let Ok(value) = calls.parse::<f64>() {
-                self.commands_total
-                    .with_label_values(&[&cmd, &self.target, &self.target_name])
-                    .set(value);
+                set_counter(
+                    &self
+                        .commands_total
+                        .with_label_values(&[cmd, target, target_name]),
+                    value,
+                    restarted,
+                );
             }
         }
 
         if let Some(rejected) = stats_map.get("rejected_calls") {
             if let Ok(value) = rejected.parse::<f64>() {
-                self.commands_rejected_total
-                    .with_label_values(&[&cmd, &self.target, &self.target_name])
-                    .set(value);
+                set_counter(
+                    &self
+                        .commands_rejected_total
+                        .with_label_values(&[cmd, target, target_name]),
+                    value,
+                    restarted,
+                );
+            }
+        }
+
+        if let Some(failed) = stats_map.get("failed_calls") {
+            if let Ok(value) = failed.parse::<f64>() {
+                set_counter(
+                    &self
+                        .commands_failed_total
+                        .with_label_values(&[cmd, target, target_name]),
+                    value,
+                    restarted,
+                );
             }
         }
 
         if let Some(usec) = stats_map.get("usec") {
             if let Ok(microseconds) = usec.parse::<f64>() {
                 let seconds = microseconds / 1_000_000.0;
-                self.commands_duration_total
-                    .with_label_values(&[&cmd, &self.target, &self.target_name])
-                    .set(seconds);
+                set_counter(
+                    &self
+                        .commands_duration_total
+                        .with_label_values(&[cmd, target, target_name]),
+                    seconds,
+                    restarted,
+                );
             }
         }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Server,
+    Clients,
+    Memory,
+    Persistence,
+    Stats,
+    Replication,
+    Cpu,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricType {
+    Gauge,
+    Counter,
+}
+
+// How the raw INFO value is turned into a sample. `Percentage` fields carry a
+// trailing `%`, and `Status` fields report 1 when the value equals the expected
+// string (e.g. `ok`) and 0 otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Number,
+    Percentage,
+    Status(&'static str),
+}
+
+// A flat `field:value` line of INFO and the metric it is exported as. The
+// exported name is `redis_<name>[_<unit>][_total]`.
+#[derive(Debug)]
+pub struct MetricSpec {
+    pub section: Section,
+    pub field: &'static str,
+    pub name: &'static str,
+    pub metric_type: MetricType,
+    pub help: &'static str,
+    pub unit: Option<&'static str>,
+    pub scale: f64,
+    pub value: ValueKind,
+}
+
+impl MetricSpec {
+    const fn gauge(
+        section: Section,
+        field: &'static str,
+        name: &'static str,
+        help: &'static str,
+    ) -> Self {
+        Self {
+            section,
+            field,
+            name,
+            metric_type: MetricType::Gauge,
+            help,
+            unit: None,
+            scale: 1.0,
+            value: ValueKind::Number,
+        }
+    }
+
+    const fn counter(
+        section: Section,
+        field: &'static str,
+        name: &'static str,
+        help: &'static str,
+    ) -> Self {
+        Self {
+            metric_type: MetricType::Counter,
+            ..Self::gauge(section, field, name, help)
+        }
+    }
+
+    const fn unit(self, unit: &'static str) -> Self {
+        Self {
+            unit: Some(unit),
+            ..self
+        }
+    }
+
+    const fn scale(self, scale: f64) -> Self {
+        Self { scale, ..self }
+    }
+
+    const fn percentage(self) -> Self {
+        Self {
+            value: ValueKind::Percentage,
+            ..self
+        }
+    }
+
+    const fn status(self, expected: &'static str) -> Self {
+        Self {
+            value: ValueKind::Status(expected),
+            ..self
+        }
+    }
 
-    fn parse_gauge_metric_name(&self, name: &str) -> Option<GaugeVec> {
-        match name {
-            "up" => Some(self.up.clone()),
-            "uptime_in_seconds" => Some(self.uptime.clone()),
-            "process_id" => Some(self.process_id.clone()),
-            "io_threads_active" => Some(self.io_threads_active.clone()),
-            "connected_clients" => Some(self.connected_clients.clone()),
-            "blocked_clients" => Some(self.blocked_clients.clone()),
-            "tracking_clients" => Some(self.tracking_clients.clone()),
-            "clients_in_timeout_table" => Some(self.clients_in_timeout_table.clone()),
-            "pubsub_clients" => Some(self.pubsub_clients.clone()),
-            "watching_clients" => Some(self.watching_clients.clone()),
-            "total_watched_keys" => Some(self.total_watched_keys.clone()),
-            "total_blocking_keys" => Some(self.total_blocking_keys.clone()),
-            "total_blocking_keys_on_nokey" => Some(self.total_blocking_keys_on_nokey.clone()),
-            "client_longest_output_list" => Some(self.client_longest_output_list.clone()),
-            "client_biggest_input_buf" => Some(self.client_biggest_input_buf.clone()),
-            "client_recent_max_output_buffer" => Some(self.client_recent_max_output_buffer.clone()),
-            "client_recent_max_input_buffer" => Some(self.client_recent_max_input_buffer.clone()),
-            "allocator_active" => Some(self.allocator_active.clone()),
-            "allocator_allocated" => Some(self.allocator_allocated.clone()),
-            "allocator_resident" => Some(self.allocator_resident.clone()),
-            "allocator_frag_ratio" => Some(self.allocator_frag_ratio.clone()),
-            "allocator_frag_bytes" => Some(self.allocator_frag_bytes.clone()),
-            "allocator_rss_ratio" => Some(self.allocator_rss_ratio.clone()),
-            "allocator_rss_bytes" => Some(self.allocator_rss_bytes.clone()),
-            "used_memory" => Some(self.used_memory.clone()),
-            "used_memory_rss" => Some(self.used_memory_rss.clone()),
-            "used_memory_peak" => Some(self.used_memory_peak.clone()),
-            "used_memory_lua" => Some(self.used_memory_lua.clone()),
-            "used_memory_vm_eval" => Some(self.used_memory_vm_eval.clone()),
-            "used_memory_scripts_eval" => Some(self.used_memory_scripts_eval.clone()),
-            "used_memory_overhead" => Some(self.used_memory_overhead.clone()),
-            "used_memory_startup" => Some(self.used_memory_startup.clone()),
-            "used_memory_dataset" => Some(self.used_memory_dataset.clone()),
-            "number_of_cached_scripts" => Some(self.number_of_cached_scripts.clone()),
-            "number_of_functions" => Some(self.number_of_functions.clone()),
-            "number_of_libraries" => Some(self.number_of_libraries.clone()),
-            "used_memory_vm_functions" => Some(self.used_memory_vm_functions.clone()),
-            "used_memory_scripts" => Some(self.used_memory_scripts.clone()),
-            "used_memory_functions" => Some(self.used_memory_functions.clone()),
-            "used_memory_vm_total" => Some(self.used_memory_vm_total.clone()),
-            "maxmemory" => Some(self.maxmemory.clone()),
-            "mem_fragmentation_ratio" => Some(self.memory_fragmentation_ratio.clone()),
-            "keyspace_hits" => Some(self.keyspace_hits.clone()),
-            "keyspace_misses" => Some(self.keyspace_misses.clone()),
-            "master_last_io_seconds_ago" => Some(self.master_last_io_seconds_ago.clone()),
-            "connected_slaves" => Some(self.connected_slaves.clone()),
-            _ => None,
+    pub fn parse(&self, value: &str) -> Option<f64> {
+        match self.value {
+            ValueKind::Number => value.parse::<f64>().ok().map(|v| v * self.scale),
+            ValueKind::Percentage => value
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .map(|v| v * self.scale),
+            ValueKind::Status(expected) => Some(if value == expected { 1.0 } else { 0.0 }),
         }
     }
 
-    fn parse_counter_metric_name(&self, name: &str) -> Option<CounterVec> {
-        match name {
-            "evicted_keys" => Some(self.evicted_keys.clone()),
-            _ => None,
+    pub fn metric_name(&self) -> String {
+        let mut name = self.name.to_string();
+        if let Some(unit) = self.unit {
+            name.push('_');
+            name.push_str(unit);
         }
+        if self.metric_type == MetricType::Counter {
+            name.push_str("_total");
+        }
+        name
+    }
+}
+
+pub fn print_metrics() {
+    println!("| Metric | Type | Section | INFO field | Description |");
+    println!("|---|---|---|---|---|");
+    for spec in INFO_METRICS {
+        println!(
+            "| `redis_{}` | {:?} | {:?} | `{}` | {} |",
+            spec.metric_name(),
+            spec.metric_type,
+            spec.section,
+            spec.field,
+            spec.help
+        );
+    }
+}
+
+use MetricSpec as M;
+use Section::*;
+
+#[rustfmt::skip]
+pub const INFO_METRICS: &[MetricSpec] = &[
+    M::gauge(Server, "uptime_in_seconds", "uptime_in_seconds", "Target uptime in seconds"),
+    M::gauge(Server, "process_id", "process_id", "Redis process ID"),
+
+    M::gauge(Clients, "connected_clients", "connected_clients", "Number of connected clients"),
+    M::gauge(Clients, "blocked_clients", "blocked_clients", "Number of blocked clients"),
+    M::gauge(Clients, "tracking_clients", "tracking_clients", "Number of tracking clients"),
+    M::gauge(Clients, "clients_in_timeout_table", "clients_in_timeout_table", "Number of client in-memory time out"),
+    M::gauge(Clients, "pubsub_clients", "pubsub_clients", "Number of pubsub clients"),
+    M::gauge(Clients, "watching_clients", "watching_clients", "Number of watching clients"),
+    M::gauge(Clients, "total_watched_keys", "total_watched_keys", "Number of total watching keys"),
+    M::gauge(Clients, "total_blocking_keys", "total_blocking_keys", "Number of blocking keys"),
+    M::gauge(Clients, "total_blocking_keys_on_nokey", "total_blocking_keys_on_nokey", "Number of blocking keys on nokey"),
+    M::gauge(Clients, "client_longest_output_list", "client_longest_output_list", "Longest output list among clients"),
+    M::gauge(Clients, "client_biggest_input_buf", "client_biggest_input_buf", "Biggest input buffer among clients"),
+    M::gauge(Clients, "client_recent_max_output_buffer", "client_recent_max_output_buffer", "Recent maximum output buffer among clients"),
+    M::gauge(Clients, "client_recent_max_input_buffer", "client_recent_max_input_buffer", "Recent maximum input buffer among clients"),
+
+    M::gauge(Memory, "allocator_active", "allocator_active", "Total size of allocated memory"),
+    M::gauge(Memory, "allocator_allocated", "allocator_allocated", "Total size of allocated memory including internal fragmentation"),
+    M::gauge(Memory, "allocator_resident", "allocator_resident", "Total size of resident memory"),
+    M::gauge(Memory, "allocator_frag_ratio", "allocator_frag_ratio", "Ratio of memory fragmentation"),
+    M::gauge(Memory, "allocator_frag_bytes", "allocator_frag_bytes", "Amount of memory fragmentation in bytes"),
+    M::gauge(Memory, "allocator_rss_ratio", "allocator_rss_ratio", "Ratio of resident set size to allocated memory"),
+    M::gauge(Memory, "allocator_rss_bytes", "allocator_rss_bytes", "Number of resident set size bytes"),
+    M::gauge(Memory, "used_memory", "memory_used", "Total number of bytes allocated by Redis").unit("bytes"),
+    M::gauge(Memory, "used_memory_rss", "memory_used_rss", "Number of bytes that Redis allocated as seen by the operating system").unit("bytes"),
+    M::gauge(Memory, "used_memory_peak", "memory_used_peak", "Peak memory consumed by Redis (in bytes)").unit("bytes"),
+    M::gauge(Memory, "used_memory_lua", "memory_used_lua", "Number of bytes used by the Lua engine").unit("bytes"),
+    M::gauge(Memory, "used_memory_vm_eval", "memory_used_vm_eval", "Memory used by VM for evaluation").unit("bytes"),
+    M::gauge(Memory, "used_memory_scripts_eval", "memory_used_scripts_eval", "Memory used for script evaluation").unit("bytes"),
+    M::gauge(Memory, "used_memory_overhead", "memory_used_overhead", "The sum in bytes of all overheads allocated by Redis").unit("bytes"),
+    M::gauge(Memory, "used_memory_startup", "memory_used_startup", "Initial amount of memory consumed by Redis at startup").unit("bytes"),
+    M::gauge(Memory, "used_memory_dataset", "memory_used_dataset", "The size in bytes of the dataset").unit("bytes"),
+    M::gauge(Memory, "number_of_cached_scripts", "number_of_cached_scripts", "Number of cached scripts"),
+    M::gauge(Memory, "number_of_functions", "number_of_functions", "Number of functions"),
+    M::gauge(Memory, "number_of_libraries", "number_of_libraries", "Number of libraries"),
+    M::gauge(Memory, "used_memory_vm_functions", "memory_used_vm_functions", "Memory used by VM functions").unit("bytes"),
+    M::gauge(Memory, "used_memory_scripts", "memory_used_scripts", "Memory used by scripts").unit("bytes"),
+    M::gauge(Memory, "used_memory_functions", "memory_used_functions", "Memory used by functions").unit("bytes"),
+    M::gauge(Memory, "used_memory_vm_total", "used_memory_vm_total", "Total memory used by VM"),
+    M::gauge(Memory, "maxmemory", "memory_max", "Maximum amount of memory Redis can use").unit("bytes"),
+    M::gauge(Memory, "mem_fragmentation_ratio", "memory_fragmentation_ratio", "Memory fragmentation ratio"),
+    M::gauge(Memory, "mem_fragmentation_bytes", "memory_fragmentation", "Difference between used_memory_rss and used_memory").unit("bytes"),
+    M::gauge(Memory, "used_memory_peak_perc", "memory_used_peak_percentage", "Percentage of used_memory_peak out of used_memory").percentage(),
+    M::gauge(Memory, "used_memory_dataset_perc", "memory_used_dataset_percentage", "Percentage of used_memory_dataset out of the net memory usage").percentage(),
+    M::gauge(Memory, "total_system_memory", "total_system_memory", "Total amount of memory of the host").unit("bytes"),
+    M::gauge(Memory, "allocator_muzzy", "allocator_muzzy", "Total size of muzzy memory in the allocator").unit("bytes"),
+    M::gauge(Memory, "rss_overhead_ratio", "rss_overhead_ratio", "Ratio of process RSS to allocator resident memory"),
+    M::gauge(Memory, "rss_overhead_bytes", "rss_overhead", "Difference between process RSS and allocator resident memory").unit("bytes"),
+    M::gauge(Memory, "mem_not_counted_for_evict", "memory_not_counted_for_evict", "Memory not counted for eviction").unit("bytes"),
+    M::gauge(Memory, "mem_replication_backlog", "memory_replication_backlog", "Memory used by the replication backlog").unit("bytes"),
+    M::gauge(Memory, "mem_total_replication_buffers", "memory_total_replication_buffers", "Total memory consumed by replication buffers").unit("bytes"),
+    M::gauge(Memory, "mem_clients_slaves", "memory_clients_slaves", "Memory used by replica clients").unit("bytes"),
+    M::gauge(Memory, "mem_clients_normal", "memory_clients_normal", "Memory used by normal clients").unit("bytes"),
+    M::gauge(Memory, "mem_cluster_links", "memory_cluster_links", "Memory used by links to peers on the cluster bus").unit("bytes"),
+    M::gauge(Memory, "mem_aof_buffer", "memory_aof_buffer", "Memory used by the AOF buffer").unit("bytes"),
+    M::gauge(Memory, "mem_overhead_db_hashtable_rehashing", "memory_overhead_db_hashtable_rehashing", "Memory used by database hash tables being rehashed").unit("bytes"),
+    M::gauge(Memory, "active_defrag_running", "active_defrag_running", "CPU percentage used by active defragmentation"),
+    M::gauge(Memory, "lazyfree_pending_objects", "lazyfree_pending_objects", "Number of objects waiting to be freed"),
+    M::counter(Memory, "lazyfreed_objects", "lazyfreed_objects", "Total number of objects that have been lazy freed"),
+
+    M::gauge(Persistence, "loading", "loading", "Whether a dump file is being loaded"),
+    M::gauge(Persistence, "async_loading", "async_loading", "Whether a replication dataset is being loaded asynchronously"),
+    M::gauge(Persistence, "loading_loaded_perc", "loading_loaded_percentage", "Percentage of the dump file loaded"),
+    M::gauge(Persistence, "loading_eta_seconds", "loading_eta_seconds", "Estimated time left to finish loading"),
+    M::gauge(Persistence, "current_cow_peak", "current_cow_peak", "Peak copy-on-write memory of the running fork").unit("bytes"),
+    M::gauge(Persistence, "current_cow_size", "current_cow_size", "Copy-on-write memory of the running fork").unit("bytes"),
+    M::gauge(Persistence, "current_fork_perc", "current_fork_percentage", "Progress of the running fork"),
+    M::gauge(Persistence, "current_save_keys_processed", "current_save_keys_processed", "Number of keys processed by the running save"),
+    M::gauge(Persistence, "current_save_keys_total", "current_save_keys", "Number of keys at the start of the running save"),
+    M::gauge(Persistence, "rdb_changes_since_last_save", "rdb_changes_since_last_save", "Number of changes since the last dump"),
+    M::gauge(Persistence, "rdb_bgsave_in_progress", "rdb_bgsave_in_progress", "Whether a RDB save is in progress"),
+    M::gauge(Persistence, "rdb_last_save_time", "rdb_last_save_timestamp", "Unix timestamp of the last successful RDB save").unit("seconds"),
+    M::gauge(Persistence, "rdb_last_bgsave_status", "rdb_last_bgsave_status", "Whether the last RDB save succeeded").status("ok"),
+    M::gauge(Persistence, "rdb_last_bgsave_time_sec", "rdb_last_bgsave_duration", "Duration of the last RDB save").unit("seconds"),
+    M::gauge(Persistence, "rdb_current_bgsave_time_sec", "rdb_current_bgsave_duration", "Duration of the running RDB save").unit("seconds"),
+    M::counter(Persistence, "rdb_saves", "rdb_saves", "Total number of RDB snapshots performed"),
+    M::gauge(Persistence, "rdb_last_cow_size", "rdb_last_cow_size", "Copy-on-write memory of the last RDB save").unit("bytes"),
+    M::gauge(Persistence, "aof_enabled", "aof_enabled", "Whether AOF logging is enabled"),
+    M::gauge(Persistence, "aof_rewrite_in_progress", "aof_rewrite_in_progress", "Whether an AOF rewrite is in progress"),
+    M::gauge(Persistence, "aof_rewrite_scheduled", "aof_rewrite_scheduled", "Whether an AOF rewrite is scheduled"),
+    M::gauge(Persistence, "aof_last_rewrite_time_sec", "aof_last_rewrite_duration", "Duration of the last AOF rewrite").unit("seconds"),
+    M::gauge(Persistence, "aof_current_rewrite_time_sec", "aof_current_rewrite_duration", "Duration of the running AOF rewrite").unit("seconds"),
+    M::gauge(Persistence, "aof_last_bgrewrite_status", "aof_last_bgrewrite_status", "Whether the last AOF rewrite succeeded").status("ok"),
+    M::counter(Persistence, "aof_rewrites", "aof_rewrites", "Total number of AOF rewrites performed"),
+    M::gauge(Persistence, "aof_rewrites_consecutive_failures", "aof_rewrites_consecutive_failures", "Number of consecutive failed AOF rewrites"),
+    M::gauge(Persistence, "aof_last_write_status", "aof_last_write_status", "Whether the last write to the AOF succeeded").status("ok"),
+    M::gauge(Persistence, "aof_last_cow_size", "aof_last_cow_size", "Copy-on-write memory of the last AOF rewrite").unit("bytes"),
+    M::gauge(Persistence, "aof_current_size", "aof_current_size", "Current AOF file size").unit("bytes"),
+    M::gauge(Persistence, "aof_base_size", "aof_base_size", "AOF file size at the last rewrite").unit("bytes"),
+    M::gauge(Persistence, "aof_pending_rewrite", "aof_pending_rewrite", "Whether an AOF rewrite is waiting for a RDB save to finish"),
+    M::gauge(Persistence, "aof_buffer_length", "aof_buffer_length", "Size of the AOF buffer").unit("bytes"),
+    M::gauge(Persistence, "aof_pending_bio_fsync", "aof_pending_bio_fsync", "Number of fsync jobs pending in the background queue"),
+    M::counter(Persistence, "aof_delayed_fsync", "aof_delayed_fsync", "Total number of delayed fsync calls"),
+    M::gauge(Persistence, "module_fork_in_progress", "module_fork_in_progress", "Whether a module fork is in progress"),
+
+    M::counter(Stats, "total_connections_received", "connections_received", "Total number of connections accepted by the server"),
+    M::counter(Stats, "total_commands_processed", "commands_processed", "Total number of commands processed by the server"),
+    M::gauge(Stats, "instantaneous_ops_per_sec", "instantaneous_ops_per_sec", "Number of commands processed per second"),
+    M::counter(Stats, "total_net_input_bytes", "net_input", "Total number of bytes read from the network").unit("bytes"),
+    M::counter(Stats, "total_net_output_bytes", "net_output", "Total number of bytes written to the network").unit("bytes"),
+    M::counter(Stats, "total_net_repl_input_bytes", "net_repl_input", "Total number of bytes read from the network for replication").unit("bytes"),
+    M::counter(Stats, "total_net_repl_output_bytes", "net_repl_output", "Total number of bytes written to the network for replication").unit("bytes"),
+    M::gauge(Stats, "instantaneous_input_kbps", "instantaneous_input_kbps", "Network read rate in KB/sec"),
+    M::gauge(Stats, "instantaneous_output_kbps", "instantaneous_output_kbps", "Network write rate in KB/sec"),
+    M::gauge(Stats, "instantaneous_input_repl_kbps", "instantaneous_input_repl_kbps", "Network read rate for replication in KB/sec"),
+    M::gauge(Stats, "instantaneous_output_repl_kbps", "instantaneous_output_repl_kbps", "Network write rate for replication in KB/sec"),
+    M::counter(Stats, "rejected_connections", "rejected_connections", "Total number of connections rejected because of maxclients"),
+    M::counter(Stats, "sync_full", "sync_full", "Total number of full resyncs with replicas"),
+    M::counter(Stats, "sync_partial_ok", "sync_partial_ok", "Total number of accepted partial resync requests"),
+    M::counter(Stats, "sync_partial_err", "sync_partial_err", "Total number of denied partial resync requests"),
+    M::counter(Stats, "expired_keys", "expired_keys", "Total number of key expiration events"),
+    M::counter(Stats, "expired_subkeys", "expired_subkeys", "Total number of hash field expiration events"),
+    M::gauge(Stats, "expired_stale_perc", "expired_stale_percentage", "Percentage of keys probably expired"),
+    M::counter(Stats, "expired_time_cap_reached_count", "expired_time_cap_reached", "Total number of times active expiry cycles stopped early"),
+    M::counter(Stats, "expire_cycle_cpu_milliseconds", "expire_cycle_cpu_time", "Total time spent on active expiry cycles").unit("seconds").scale(0.001),
+    M::counter(Stats, "evicted_keys", "evicted_keys", "Total number of evicted keys"),
+    M::counter(Stats, "evicted_clients", "evicted_clients", "Total number of clients evicted because of maxmemory-clients"),
+    M::counter(Stats, "evicted_scripts", "evicted_scripts", "Total number of Lua scripts evicted"),
+    M::counter(Stats, "total_eviction_exceeded_time", "eviction_exceeded_time", "Total time used_memory was greater than maxmemory").unit("seconds").scale(0.001),
+    M::gauge(Stats, "current_eviction_exceeded_time", "current_eviction_exceeded_time", "Time used_memory has been greater than maxmemory").unit("seconds").scale(0.001),
+    M::counter(Stats, "keyspace_hits", "keyspace_hits", "Total number of keyspace hits"),
+    M::counter(Stats, "keyspace_misses", "keyspace_misses", "Total number of keyspace misses"),
+    M::gauge(Stats, "pubsub_channels", "pubsub_channels", "Number of pub/sub channels with client subscriptions"),
+    M::gauge(Stats, "pubsub_patterns", "pubsub_patterns", "Number of pub/sub patterns with client subscriptions"),
+    M::gauge(Stats, "pubsubshard_channels", "pubsubshard_channels", "Number of pub/sub shard channels with client subscriptions"),
+    M::gauge(Stats, "latest_fork_usec", "latest_fork_duration", "Duration of the latest fork").unit("seconds").scale(0.000_001),
+    M::counter(Stats, "total_forks", "forks", "Total number of fork operations"),
+    M::gauge(Stats, "migrate_cached_sockets", "migrate_cached_sockets", "Number of sockets open for MIGRATE"),
+    M::gauge(Stats, "slave_expires_tracked_keys", "slave_expires_tracked_keys", "Number of keys tracked for expiry on a writable replica"),
+    M::counter(Stats, "active_defrag_hits", "active_defrag_hits", "Total number of value reallocations performed by active defrag"),
+    M::counter(Stats, "active_defrag_misses", "active_defrag_misses", "Total number of aborted value reallocations started by active defrag"),
+    M::counter(Stats, "active_defrag_key_hits", "active_defrag_key_hits", "Total number of keys that were actively defragmented"),
+    M::counter(Stats, "active_defrag_key_misses", "active_defrag_key_misses", "Total number of keys that were skipped by active defrag"),
+    M::counter(Stats, "total_active_defrag_time", "active_defrag_time", "Total time memory fragmentation was over the limit").unit("seconds").scale(0.001),
+    M::gauge(Stats, "current_active_defrag_time", "current_active_defrag_time", "Time memory fragmentation has been over the limit").unit("seconds").scale(0.001),
+    M::gauge(Stats, "tracking_total_keys", "tracking_total_keys", "Number of keys tracked by the server"),
+    M::gauge(Stats, "tracking_total_items", "tracking_total_items", "Number of items tracked by the server"),
+    M::gauge(Stats, "tracking_total_prefixes", "tracking_total_prefixes", "Number of tracked prefixes in the server's prefix table"),
+    M::counter(Stats, "total_error_replies", "error_replies", "Total number of error replies"),
+    M::counter(Stats, "unexpected_error_replies", "unexpected_error_replies", "Total number of unexpected error replies"),
+    M::counter(Stats, "dump_payload_sanitizations", "dump_payload_sanitizations", "Total number of deep integrity validations of DUMP payloads"),
+    M::counter(Stats, "total_reads_processed", "reads_processed", "Total number of read events processed"),
+    M::counter(Stats, "total_writes_processed", "writes_processed", "Total number of write events processed"),
+    M::gauge(Stats, "io_threads_active", "io_threads_active", "Number of IO threads active"),
+    M::counter(Stats, "io_threaded_reads_processed", "io_threaded_reads_processed", "Total number of read events processed by IO threads"),
+    M::counter(Stats, "io_threaded_writes_processed", "io_threaded_writes_processed", "Total number of write events processed by IO threads"),
+    M::counter(Stats, "client_query_buffer_limit_disconnections", "client_query_buffer_limit_disconnections", "Total number of disconnections due to the client query buffer limit"),
+    M::counter(Stats, "client_output_buffer_limit_disconnections", "client_output_buffer_limit_disconnections", "Total number of disconnections due to the client output buffer limit"),
+    M::counter(Stats, "reply_buffer_shrinks", "reply_buffer_shrinks", "Total number of output buffer shrinks"),
+    M::counter(Stats, "reply_buffer_expands", "reply_buffer_expands", "Total number of output buffer expands"),
+    M::counter(Stats, "eventloop_cycles", "eventloop_cycles", "Total number of eventloop cycles"),
+    M::counter(Stats, "eventloop_duration_sum", "eventloop_duration", "Total time spent in the eventloop").unit("seconds").scale(0.000_001),
+    M::counter(Stats, "eventloop_duration_cmd_sum", "eventloop_duration_cmd", "Total time spent on executing commands").unit("seconds").scale(0.000_001),
+    M::gauge(Stats, "instantaneous_eventloop_cycles_per_sec", "instantaneous_eventloop_cycles_per_sec", "Number of eventloop cycles per second"),
+    M::gauge(Stats, "instantaneous_eventloop_duration_usec", "instantaneous_eventloop_duration", "Average time spent in a single eventloop cycle").unit("seconds").scale(0.000_001),
+    M::counter(Stats, "acl_access_denied_auth", "acl_access_denied_auth", "Total number of authentication failures"),
+    M::counter(Stats, "acl_access_denied_cmd", "acl_access_denied_cmd", "Total number of commands rejected because of ACL rules"),
+    M::counter(Stats, "acl_access_denied_key", "acl_access_denied_key", "Total number of commands rejected because of key ACL rules"),
+    M::counter(Stats, "acl_access_denied_channel", "acl_access_denied_channel", "Total number of commands rejected because of channel ACL rules"),
+
+    M::gauge(Replication, "connected_slaves", "connected_slaves", "Number of connected slaves"),
+    M::gauge(Replication, "master_last_io_seconds_ago", "master_last_io_seconds_ago", "Master last io seconds ago"),
+    M::gauge(Replication, "master_link_status", "master_link_up", "Whether the link to the master is up").status("up"),
+    M::gauge(Replication, "master_sync_in_progress", "master_sync_in_progress", "Whether the master is syncing to the replica"),
+    M::gauge(Replication, "master_sync_total_bytes", "master_sync_total", "Total size of the payload transferred by the running sync").unit("bytes"),
+    M::gauge(Replication, "master_sync_read_bytes", "master_sync_read", "Number of bytes already transferred by the running sync").unit("bytes"),
+    M::gauge(Replication, "master_sync_left_bytes", "master_sync_left", "Number of bytes left before the running sync completes").unit("bytes"),
+    M::gauge(Replication, "master_sync_last_io_seconds_ago", "master_sync_last_io_seconds_ago", "Seconds since the last transfer I/O of the running sync"),
+    M::gauge(Replication, "master_link_down_since_seconds", "master_link_down_since_seconds", "Seconds since the link to the master went down"),
+    M::gauge(Replication, "slave_repl_offset", "slave_repl_offset", "Replication offset of the replica"),
+    M::gauge(Replication, "slave_read_repl_offset", "slave_read_repl_offset", "Read replication offset of the replica"),
+    M::gauge(Replication, "slave_priority", "slave_priority", "Priority of the replica as a failover candidate"),
+    M::gauge(Replication, "slave_read_only", "slave_read_only", "Whether the replica is read-only"),
+    M::gauge(Replication, "min_slaves_good_slaves", "min_slaves_good_slaves", "Number of replicas considered good"),
+    M::gauge(Replication, "master_repl_offset", "master_repl_offset", "Replication offset of the server"),
+    M::gauge(Replication, "second_repl_offset", "second_repl_offset", "Offset up to which replication IDs are accepted"),
+    M::gauge(Replication, "repl_backlog_active", "repl_backlog_active", "Whether the replication backlog is active"),
+    M::gauge(Replication, "repl_backlog_size", "repl_backlog_size", "Total size of the replication backlog").unit("bytes"),
+    M::gauge(Replication, "repl_backlog_first_byte_offset", "repl_backlog_first_byte_offset", "Master offset of the replication backlog"),
+    M::gauge(Replication, "repl_backlog_histlen", "repl_backlog_history", "Size of the data in the replication backlog").unit("bytes"),
+
+    M::counter(Cpu, "used_cpu_sys", "cpu_sys", "System CPU consumed by the Redis server").unit("seconds"),
+    M::counter(Cpu, "used_cpu_user", "cpu_user", "User CPU consumed by the Redis server").unit("seconds"),
+    M::counter(Cpu, "used_cpu_sys_children", "cpu_sys_children", "System CPU consumed by the background processes").unit("seconds"),
+    M::counter(Cpu, "used_cpu_user_children", "cpu_user_children", "User CPU consumed by the background processes").unit("seconds"),
+    M::counter(Cpu, "used_cpu_sys_main_thread", "cpu_sys_main_thread", "System CPU consumed by the Redis main thread").unit("seconds"),
+    M::counter(Cpu, "used_cpu_user_main_thread", "cpu_user_main_thread", "User CPU consumed by the Redis main thread").unit("seconds"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::io;
+
+    fn gauge_value(registry: &Registry, name: &str, target: &str) -> Option<f64> {
+        registry
+            .gather()
+            .iter()
+            .find(|family| family.get_name() == name)?
+            .get_metric()
+            .iter()
+            .find(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_name() == "target" && label.get_value() == target)
+            })
+            .map(|metric| metric.get_gauge().get_value())
+    }
+
+    #[test]
+    fn info_metrics_are_unique() {
+        let mut fields = HashSet::new();
+        let mut names = HashSet::new();
+        for spec in INFO_METRICS {
+            assert!(fields.insert(spec.field), "duplicate field {}", spec.field);
+            assert!(
+                names.insert(spec.metric_name()),
+                "duplicate metric {}",
+                spec.metric_name()
+            );
+        }
+    }
+
+    #[test]
+    fn multiple_targets_share_one_registry() {
+        let registry = Registry::new();
+        let metrics = Arc::new(Metrics::new(&registry).unwrap());
+
+        let targets = [
+            ("redis://10.0.0.1:6379", "cache-1", 10),
+            ("redis://10.0.0.2:6379", "cache-2", 20),
+            ("redis://10.0.0.3:6379", "cache-3", 30),
+        ];
+
+        // Collectors only share the families, registering them per target
+        // would fail with AlreadyReg
+        let families = registry.gather().len();
+        let collectors: Vec<Collector> = targets
+            .iter()
+            .map(|(target, target_name, _)| {
+                let config = connection::Config {
+                    name: Some(target_name.to_string()),
+                    url: target.to_string(),
+                    ..Default::default()
+                };
+                Collector::new(Target::new(&config).unwrap(), metrics.clone(), &config)
+            })
+            .collect();
+        assert_eq!(collectors.len(), targets.len());
+        assert_eq!(registry.gather().len(), families);
+        assert!(Metrics::new(&registry).is_err());
+        for (target, target_name, clients) in targets {
+            let info = format!(
+                "# Clients\r\nconnected_clients:{}\r\n\r\n# Keyspace\r\ndb0:keys=5,expires=1,avg_ttl=0\r\n",
+                clients
+            );
+            metrics.update(target, target_name, None, None, &info);
+        }
+
+        for (target, _, clients) in targets {
+            assert_eq!(gauge_value(&registry, "redis_up", target), Some(1.0));
+            assert_eq!(
+                gauge_value(&registry, "redis_connected_clients", target),
+                Some(clients as f64)
+            );
+        }
+
+        let families = registry.gather();
+        let db_keys = families
+            .iter()
+            .find(|family| family.get_name() == "redis_db_keys")
+            .unwrap();
+        assert_eq!(db_keys.get_metric().len(), targets.len());
+    }
+
+    #[test]
+    fn counters_mirror_absolute_values() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let counter = |registry: &Registry| {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == "redis_evicted_keys_total")
+                .map(|family| family.get_metric()[0].get_counter().get_value())
+        };
+
+        metrics.update("t", "t", None, None, "run_id:a\r\nevicted_keys:10\r\n");
+        metrics.update("t", "t", None, None, "run_id:a\r\nevicted_keys:15\r\n");
+        assert_eq!(counter(&registry), Some(15.0));
+
+        metrics.update("t", "t", None, None, "run_id:b\r\nevicted_keys:20\r\n");
+        assert_eq!(counter(&registry), Some(20.0));
+
+        metrics.update("t", "t", None, None, "run_id:b\r\nevicted_keys:3\r\n");
+        assert_eq!(counter(&registry), Some(3.0));
+    }
+
+    #[test]
+    fn status_fields_are_decoded() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+
+        metrics.update(
+            "t",
+            "t",
+            None,
+            None,
+            "rdb_last_bgsave_status:ok\r\naof_last_write_status:err\r\n",
+        );
+        assert_eq!(
+            gauge_value(&registry, "redis_rdb_last_bgsave_status", "t"),
+            Some(1.0)
+        );
+        assert_eq!(
+            gauge_value(&registry, "redis_aof_last_write_status", "t"),
+            Some(0.0)
+        );
+    }
+
+    #[test]
+    fn disconnected_replicas_are_removed() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let replicas = |registry: &Registry| {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == "redis_connected_slave_replication_lag_bytes")
+                .map(|family| family.get_metric().len())
+                .unwrap_or(0)
+        };
+
+        metrics.update(
+            "t",
+            "t",
+            None,
+            None,
+            "master_repl_offset:100\r\n\
+             slave0:ip=10.0.0.2,port=6379,state=online,offset=90,lag=0\r\n\
+             slave1:ip=10.0.0.3,port=6379,state=online,offset=100,lag=1\r\n",
+        );
+        assert_eq!(replicas(&registry), 2);
+
+        metrics.update(
+            "t",
+            "t",
+            None,
+            None,
+            "master_repl_offset:120\r\n\
+             slave0:ip=10.0.0.2,port=6379,state=online,offset=110,lag=0\r\n",
+        );
+        assert_eq!(replicas(&registry), 1);
+    }
+
+    #[test]
+    fn failed_scrapes_are_reported_by_kind() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let errors = |kind: &str| {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == "redis_exporter_scrape_errors_total")
+                .and_then(|family| {
+                    family.get_metric().iter().find(|metric| {
+                        metric
+                            .get_label()
+                            .iter()
+                            .any(|label| label.get_name() == "kind" && label.get_value() == kind)
+                    })
+                })
+                .map(|metric| metric.get_counter().get_value())
+        };
+
+        let refused = RedisError::from(io::Error::from(io::ErrorKind::ConnectionRefused));
+        let timeout = RedisError::from(io::Error::from(io::ErrorKind::TimedOut));
+        let auth = RedisError::from((ErrorKind::AuthenticationFailed, "invalid password"));
+        let command = RedisError::from((ErrorKind::ResponseError, "unknown command"));
+        assert_eq!(error_kind(&refused), "connection");
+        assert_eq!(error_kind(&timeout), "timeout");
+        assert_eq!(error_kind(&auth), "auth");
+        assert_eq!(error_kind(&command), "command");
+
+        metrics.update("t", "t", None, None, "connected_clients:1\r\n");
+        metrics.record_scrape("t", "t", Duration::from_millis(5), None);
+        assert_eq!(gauge_value(&registry, "redis_up", "t"), Some(1.0));
+        assert_eq!(
+            gauge_value(&registry, "redis_exporter_last_scrape_success", "t"),
+            Some(1.0)
+        );
+
+        metrics.record_scrape("t", "t", Duration::from_millis(5), Some(&refused));
+        metrics.record_scrape("t", "t", Duration::from_millis(5), Some(&refused));
+        assert_eq!(gauge_value(&registry, "redis_up", "t"), Some(0.0));
+        assert_eq!(
+            gauge_value(&registry, "redis_exporter_last_scrape_success", "t"),
+            Some(0.0)
+        );
+        assert_eq!(errors("connection"), Some(2.0));
+        assert_eq!(errors("command"), None);
+    }
+
+    #[test]
+    fn keyspace_is_parsed_per_database() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let db_value = |name: &str, db: &str| {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == name)?
+                .get_metric()
+                .iter()
+                .find(|metric| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .any(|label| label.get_name() == "db" && label.get_value() == db)
+                })
+                .map(|metric| metric.get_gauge().get_value())
+        };
+
+        metrics.update(
+            "t",
+            "t",
+            None,
+            Some(4),
+            "# Keyspace\r\n\
+             db0:keys=5,expires=2,avg_ttl=1500,subexpiry=1\r\n\
+             db2:keys=1,expires=0,avg_ttl=0,subexpiry=0\r\n",
+        );
+        assert_eq!(db_value("redis_db_keys", "0"), Some(5.0));
+        assert_eq!(db_value("redis_db_keys_expiring", "0"), Some(2.0));
+        assert_eq!(db_value("redis_db_avg_ttl_seconds", "0"), Some(1.5));
+        assert_eq!(db_value("redis_db_keys_subexpiry", "0"), Some(1.0));
+        assert_eq!(db_value("redis_db_keys", "2"), Some(1.0));
+        assert_eq!(db_value("redis_db_keys", "db0"), None);
+
+        // Databases missing from INFO keyspace are zero-filled
+        for name in [
+            "redis_db_keys",
+            "redis_db_keys_expiring",
+            "redis_db_avg_ttl_seconds",
+            "redis_db_keys_subexpiry",
+        ] {
+            assert_eq!(db_value(name, "1"), Some(0.0), "{}", name);
+            assert_eq!(db_value(name, "3"), Some(0.0), "{}", name);
+        }
+        assert_eq!(db_value("redis_db_keys", "4"), None);
+    }
+
+    #[test]
+    fn latency_percentiles_are_filtered() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let quantiles = |registry: &Registry| {
+            let mut quantiles: Vec<(String, f64)> = registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == "redis_latency_percentiles_usec")
+                .map(|family| {
+                    family
+                        .get_metric()
+                        .iter()
+                        .map(|metric| {
+                            let quantile = metric
+                                .get_label()
+                                .iter()
+                                .find(|label| label.get_name() == "quantile")
+                                .unwrap()
+                                .get_value()
+                                .to_string();
+                            (quantile, metric.get_gauge().get_value())
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            quantiles.sort_by(|a, b| a.0.cmp(&b.0));
+            quantiles
+        };
+
+        metrics.update(
+            "t",
+            "t",
+            Some(&[50.0, 99.9]),
+            None,
+            "# Latencystats\r\nlatency_percentiles_usec_get:p50=1.003,p99=2.007,p99.9=3.010\r\n",
+        );
+        assert_eq!(
+            quantiles(&registry),
+            vec![("0.5".to_string(), 1.003), ("0.999".to_string(), 3.010)]
+        );
+
+        // GET is no longer reported after CONFIG RESETSTAT
+        metrics.update(
+            "t",
+            "t",
+            Some(&[50.0, 99.9]),
+            None,
+            "# Latencystats\r\nlatency_percentiles_usec_set:p50=2.001\r\n",
+        );
+        assert_eq!(quantiles(&registry), vec![("0.5".to_string(), 2.001)]);
+    }
+
+    #[tokio::test]
+    async fn unreachable_target_is_reported_within_the_connection_timeout() {
+        let registry = Registry::new();
+        let metrics = Arc::new(Metrics::new(&registry).unwrap());
+        let config = connection::Config {
+            url: "redis://127.0.0.1:1".to_string(),
+            connection_timeout_ms: 1000,
+            ..Default::default()
+        };
+        let collector = Collector::new(Target::new(&config).unwrap(), metrics, &config);
+
+        let start = Instant::now();
+        assert!(collector.collect().await.is_err());
+        assert!(start.elapsed() < Duration::from_millis(config.connection_timeout_ms));
+        assert_eq!(gauge_value(&registry, "redis_up", &config.url), Some(0.0));
+
+        // Other collectors of the target do not wait for another attempt
+        let start = Instant::now();
+        assert!(collector.collect().await.is_err());
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn instance_info_follows_the_role() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let series = |registry: &Registry| {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == "redis_instance_info")
+                .map(|family| {
+                    family
+                        .get_metric()
+                        .iter()
+                        .map(|metric| {
+                            metric
+                                .get_label()
+                                .iter()
+                                .find(|label| label.get_name() == "role")
+                                .unwrap()
+                                .get_value()
+                                .to_string()
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        metrics.update(
+            "t",
+            "t",
+            None,
+            None,
+            "# Server\r\nredis_version:7.2.4\r\nrun_id:a\r\n# Replication\r\nrole:master\r\n",
+        );
+        assert_eq!(series(&registry), vec!["master"]);
+
+        // Failover turned the instance into a replica
+        metrics.update(
+            "t",
+            "t",
+            None,
+            None,
+            "# Server\r\nredis_version:7.2.4\r\nrun_id:a\r\n# Replication\r\nrole:slave\r\n",
+        );
+        assert_eq!(series(&registry), vec!["slave"]);
+        assert_eq!(
+            gauge_value(&registry, "redis_instance_info", "t"),
+            Some(1.0)
+        );
+    }
+
+    #[test]
+    fn errorstats_and_failed_calls_are_counted() {
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        let counter = |name: &str, label: (&str, &str)| {
+            registry
+                .gather()
+                .iter()
+                .find(|family| family.get_name() == name)?
+                .get_metric()
+                .iter()
+                .find(|metric| {
+                    metric
+                        .get_label()
+                        .iter()
+                        .any(|l| l.get_name() == label.0 && l.get_value() == label.1)
+                })
+                .map(|metric| metric.get_counter().get_value())
+        };
+        let info = |run_id: &str, errors: u64, failed_calls: u64| {
+            format!(
+                "run_id:{}\r\n\
+                 # Commandstats\r\n\
+                 cmdstat_get:calls=10,usec=50,usec_per_call=5.00,rejected_calls=0,failed_calls={}\r\n\
+                 # Errorstats\r\n\
+                 errorstat_ERR:count={}\r\n",
+                run_id, failed_calls, errors
+            )
+        };
+
+        metrics.update("t", "t", None, None, &info("a", 3, 2));
+        assert_eq!(counter("redis_errors_total", ("err", "ERR")), Some(3.0));
+        assert_eq!(
+            counter("redis_commands_failed_calls_total", ("cmd", "get")),
+            Some(2.0)
+        );
+
+        metrics.update("t", "t", None, None, &info("a", 5, 4));
+        assert_eq!(counter("redis_errors_total", ("err", "ERR")), Some(5.0));
+        assert_eq!(
+            counter("redis_commands_failed_calls_total", ("cmd", "get")),
+            Some(4.0)
+        );
+
+        // Counters start over with the restarted server
+        metrics.update("t", "t", None, None, &info("b", 1, 0));
+        assert_eq!(counter("redis_errors_total", ("err", "ERR")), Some(1.0));
+        assert_eq!(
+            counter("redis_commands_failed_calls_total", ("cmd", "get")),
+            Some(0.0)
+        );
     }
 }
@@ -1,2 +1,15 @@
+pub mod acl;
+pub mod clients;
+pub mod collector;
+pub mod config_get;
 pub mod connection;
+pub mod drift;
+pub mod keys;
+pub mod latency_histogram;
+pub mod latency_monitor;
+pub mod malloc_stats;
+pub mod memory_stats;
 pub mod metrics;
+mod reply;
+pub mod security;
+pub mod slowlog;
@@ -0,0 +1,254 @@
+use crate::prometheus::series::SnapshotGaugeVec;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::debug;
+use prometheus::{opts, Registry};
+use redis::aio::ConnectionManager;
+use redis::{RedisResult, Value};
+use std::sync::Arc;
+
+// Commands checked with ACL DRYRUN, with arguments that satisfy their arity.
+const DANGEROUS_COMMANDS: [&[&str]; 4] = [
+    &["FLUSHALL"],
+    &["CONFIG", "SET", "maxmemory", "0"],
+    &["DEBUG", "SLEEP", "0"],
+    &["KEYS", "*"],
+];
+
+pub struct Collector {
+    target: Target,
+    tls: bool,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(target: Target, metrics: Arc<Metrics>) -> Self {
+        Self {
+            tls: target.url.starts_with("rediss://"),
+            target,
+            metrics,
+        }
+    }
+
+    // Whether the exporter's own user may run each dangerous command. Skipped
+    // when ACL DRYRUN (Redis 7+) is not available to that user.
+    async fn dangerous_commands(&self, conn: &mut ConnectionManager) -> Vec<(String, bool)> {
+        let user: String = match redis::cmd("ACL").arg("WHOAMI").query_async(conn).await {
+            Ok(user) => user,
+            Err(e) => {
+                debug!(
+                    "Skipping dangerous commands check of {}: {}",
+                    &self.target.name, e
+                );
+                return vec![];
+            }
+        };
+
+        let mut checks = Vec::with_capacity(DANGEROUS_COMMANDS.len());
+        for command in DANGEROUS_COMMANDS {
+            let result: RedisResult<Value> = redis::cmd("ACL")
+                .arg("DRYRUN")
+                .arg(&user)
+                .arg(command)
+                .query_async(conn)
+                .await;
+            let allowed = match result {
+                // A denied command is described in a bulk string reply
+                Ok(value) => reply::as_string(&value).as_deref() == Some("OK"),
+                // Renamed or disabled with rename-command
+                Err(e) if e.to_string().contains("not found") => false,
+                Err(e) => {
+                    debug!(
+                        "Skipping dangerous commands check of {}: {}",
+                        &self.target.name, e
+                    );
+                    return vec![];
+                }
+            };
+            let check = format!("dangerous_command_{}", command[0].to_lowercase());
+            checks.push((check, allowed));
+        }
+        checks
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "security";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let mut conn = self.target.conn.get().await?;
+        // Each parameter is queried on its own, so a renamed or denied CONFIG
+        // only skips the checks that depend on it
+        let protected_mode = config_get(&mut conn, "protected-mode").await;
+        let bind = config_get(&mut conn, "bind").await;
+        let tls_port = config_get(&mut conn, "tls-port").await;
+
+        debug!("Receive data from CONFIG GET command for security checks");
+        let evaluated = [
+            (
+                "protected_mode_off",
+                protected_mode.map(|value| value.as_deref() == Some("no")),
+            ),
+            (
+                "bind_all_interfaces",
+                bind.map(|value| binds_all_interfaces(&value.unwrap_or_default())),
+            ),
+            (
+                "tls_disabled",
+                match self.tls {
+                    true => Ok(false),
+                    false => tls_port.map(|port| port.is_none_or(|port| port == "0")),
+                },
+            ),
+            ("default_user_nopass", check_default_user(&mut conn).await),
+        ];
+        let mut checks: Vec<(String, bool)> = evaluated
+            .into_iter()
+            .filter_map(|(check, result)| match result {
+                Ok(failed) => Some((check.to_string(), failed)),
+                Err(e) => {
+                    debug!("Skipping {} check of {}: {}", check, &self.target.name, e);
+                    None
+                }
+            })
+            .collect();
+
+        checks.extend(self.dangerous_commands(&mut conn).await);
+
+        self.metrics
+            .update(&self.target.url, &self.target.name, &checks);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    checks: SnapshotGaugeVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            checks: SnapshotGaugeVec::new(
+                opts!(
+                    "security_check",
+                    "Whether a security check failed (1) or passed (0)"
+                )
+                .namespace("redis"),
+                &["check", "target", "target_name"],
+                registry,
+            )?,
+        })
+    }
+
+    fn update(&self, target: &str, target_name: &str, checks: &[(String, bool)]) {
+        let series = checks
+            .iter()
+            .map(|(check, failed)| {
+                (
+                    vec![check.clone(), target.to_string(), target_name.to_string()],
+                    if *failed { 1f64 } else { 0f64 },
+                )
+            })
+            .collect();
+        self.checks.set_target(target, series);
+    }
+}
+
+// Value of a configuration parameter, None when the parameter is unknown.
+async fn config_get(conn: &mut ConnectionManager, parameter: &str) -> RedisResult<Option<String>> {
+    let reply: Value = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg(parameter)
+        .query_async(conn)
+        .await?;
+    Ok(config_value(&reply))
+}
+
+// Redis before 6 has no ACL, the default user is then only protected by
+// requirepass, whose value is dropped once compared.
+async fn check_default_user(conn: &mut ConnectionManager) -> RedisResult<bool> {
+    let result: RedisResult<Value> = redis::cmd("ACL")
+        .arg("GETUSER")
+        .arg("default")
+        .query_async(conn)
+        .await;
+    match result {
+        Ok(user) => Ok(default_user_nopass(&user)),
+        Err(e) if e.to_string().contains("unknown command") => {
+            let requirepass = config_get(conn, "requirepass").await?;
+            Ok(requirepass.unwrap_or_default().is_empty())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn config_value(reply: &Value) -> Option<String> {
+    let (_, value) = reply::as_pairs(reply).into_iter().next()?;
+    reply::as_string(value)
+}
+
+// An empty bind listens on every interface, like `0.0.0.0` and `*`. The `-`
+// prefix only makes the address optional.
+fn binds_all_interfaces(bind: &str) -> bool {
+    bind.trim().is_empty()
+        || bind
+            .split_whitespace()
+            .map(|address| address.trim_start_matches('-'))
+            .any(|address| matches!(address, "0.0.0.0" | "*" | "::" | "::*"))
+}
+
+fn default_user_nopass(user: &Value) -> bool {
+    let flags: Vec<String> = reply::as_pairs(user)
+        .into_iter()
+        .find(|(key, _)| reply::as_string(key).as_deref() == Some("flags"))
+        .map(|(_, flags)| match flags {
+            Value::Array(flags) | Value::Set(flags) => {
+                flags.iter().filter_map(reply::as_string).collect()
+            }
+            _ => vec![],
+        })
+        .unwrap_or_default();
+    flags.iter().any(|f| f == "on") && flags.iter().any(|f| f == "nopass")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulk(s: &str) -> Value {
+        Value::BulkString(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn checks_are_evaluated() {
+        assert!(binds_all_interfaces("* -::*"));
+        assert!(binds_all_interfaces("0.0.0.0"));
+        assert!(binds_all_interfaces(""));
+        assert!(!binds_all_interfaces("127.0.0.1 -::1"));
+
+        let user = |flags: &[&str]| {
+            Value::Array(vec![
+                bulk("flags"),
+                Value::Array(flags.iter().map(|f| bulk(f)).collect()),
+                bulk("passwords"),
+                Value::Array(vec![]),
+            ])
+        };
+        assert!(default_user_nopass(&user(&[
+            "on",
+            "nopass",
+            "sanitize-payload"
+        ])));
+        assert!(!default_user_nopass(&user(&["off", "nopass"])));
+        assert!(!default_user_nopass(&user(&["on"])));
+
+        let reply = Value::Array(vec![bulk("protected-mode"), bulk("no")]);
+        assert_eq!(config_value(&reply).as_deref(), Some("no"));
+        assert_eq!(config_value(&Value::Array(vec![])), None);
+    }
+}
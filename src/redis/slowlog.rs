@@ -0,0 +1,366 @@
+use crate::events::sink::SlowlogEvent;
+use crate::redis::collector::{Collect, Target};
+use crate::redis::reply;
+use log::{debug, warn};
+use prometheus::{
+    histogram_opts, opts, register_counter_vec_with_registry, register_gauge_vec_with_registry,
+    register_histogram_vec_with_registry, CounterVec, GaugeVec, HistogramVec, Registry,
+};
+use redis::{RedisResult, Value};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+const DURATION_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub entries: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { entries: 128 }
+    }
+}
+
+pub struct Collector {
+    target: Target,
+    config: Config,
+    // None until the first collection has recorded a baseline, which may be
+    // an empty slow log.
+    seen: Mutex<Option<Seen>>,
+    events: Option<mpsc::Sender<SlowlogEvent>>,
+    metrics: Arc<Metrics>,
+}
+
+impl Collector {
+    pub fn new(
+        target: Target,
+        metrics: Arc<Metrics>,
+        slowlog: &Config,
+        events: Option<mpsc::Sender<SlowlogEvent>>,
+    ) -> Self {
+        Self {
+            target,
+            config: slowlog.clone(),
+            seen: Mutex::new(None),
+            events,
+            metrics,
+        }
+    }
+
+    // Slow log entries are listed newest first, events are sent oldest first.
+    fn forward(&self, new_entries: Vec<Entry>) {
+        let Some(events) = &self.events else {
+            return;
+        };
+
+        for entry in new_entries.into_iter().rev() {
+            let event = SlowlogEvent {
+                target: self.target.url.clone(),
+                target_name: self.target.name.clone(),
+                id: entry.id,
+                timestamp: entry.timestamp,
+                duration_usec: entry.duration_usec,
+                command: entry.command(),
+                args: entry.args.into_iter().skip(1).collect(),
+                client_addr: entry.client_addr,
+                client_name: entry.client_name,
+            };
+            if let Err(e) = events.try_send(event) {
+                warn!(
+                    "Dropping slow log entry {} of {}: {}",
+                    entry.id, &self.target.name, e
+                );
+            }
+        }
+    }
+}
+
+impl Collect for Collector {
+    const NAME: &'static str = "slowlog";
+
+    fn target(&self) -> &Target {
+        &self.target
+    }
+
+    async fn collect(&self) -> RedisResult<()> {
+        let (server, length, entries): (String, u64, Value) = redis::pipe()
+            .cmd("INFO")
+            .arg("server")
+            .cmd("SLOWLOG")
+            .arg("LEN")
+            .cmd("SLOWLOG")
+            .arg("GET")
+            .arg(self.config.entries)
+            .query_async(&mut self.target.conn.get().await?)
+            .await?;
+
+        debug!("Receive data from SLOWLOG command");
+        let entries = parse_entries(&entries);
+        let fetched = entries.len() as u64;
+        let run_id = server
+            .lines()
+            .find_map(|line| line.strip_prefix("run_id:"))
+            .unwrap_or_default();
+        let mut seen = self.seen.lock().unwrap();
+        let baseline_taken = seen.is_some();
+        let new_entries = new_entries(&mut seen, run_id, entries);
+        if baseline_taken && fetched == self.config.entries && new_entries.len() as u64 == fetched {
+            warn!(
+                "More than {} slow log entries were added to {} since the last collection, some were missed",
+                self.config.entries, &self.target.name
+            );
+        }
+
+        self.metrics.update(
+            &self.target.url,
+            &self.target.name,
+            length,
+            seen.as_ref().and_then(|seen| seen.last_id),
+            &new_entries,
+        );
+        self.forward(new_entries);
+
+        Ok(())
+    }
+}
+
+pub struct Metrics {
+    length: GaugeVec,
+    last_id: GaugeVec,
+    entries_total: CounterVec,
+    duration: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        Ok(Self {
+            length: register_gauge_vec_with_registry!(
+                opts!("slowlog_length", "Number of entries in the slow log").namespace("redis"),
+                &["target", "target_name"],
+                registry
+            )?,
+            last_id: register_gauge_vec_with_registry!(
+                opts!("slowlog_last_id", "ID of the most recent slow log entry").namespace("redis"),
+                &["target", "target_name"],
+                registry
+            )?,
+            entries_total: register_counter_vec_with_registry!(
+                opts!(
+                    "slowlog_entries_total",
+                    "Total number of slow log entries seen by the exporter per command"
+                )
+                .namespace("redis"),
+                &["cmd", "target", "target_name"],
+                registry
+            )?,
+            duration: register_histogram_vec_with_registry!(
+                histogram_opts!(
+                    "slowlog_duration_seconds",
+                    "Execution time of the slow log entries seen by the exporter",
+                    DURATION_BUCKETS.to_vec()
+                )
+                .namespace("redis"),
+                &["target", "target_name"],
+                registry
+            )?,
+        })
+    }
+
+    fn update(
+        &self,
+        target: &str,
+        target_name: &str,
+        length: u64,
+        last_id: Option<u64>,
+        new_entries: &[Entry],
+    ) {
+        self.length
+            .with_label_values(&[target, target_name])
+            .set(length as f64);
+        if let Some(last_id) = last_id {
+            self.last_id
+                .with_label_values(&[target, target_name])
+                .set(last_id as f64);
+        }
+
+        let duration = self.duration.with_label_values(&[target, target_name]);
+        for entry in new_entries {
+            self.entries_total
+                .with_label_values(&[&entry.command(), target, target_name])
+                .inc();
+            duration.observe(entry.duration_usec as f64 / 1_000_000.0);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub id: u64,
+    pub timestamp: u64,
+    pub duration_usec: u64,
+    pub args: Vec<String>,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+impl Entry {
+    pub fn command(&self) -> String {
+        self.args
+            .first()
+            .map(|cmd| cmd.to_lowercase())
+            .unwrap_or_default()
+    }
+}
+
+// Each entry of SLOWLOG GET is an array of id, timestamp, duration in
+// microseconds, arguments and, since Redis 4, client address and name.
+fn parse_entries(reply: &Value) -> Vec<Entry> {
+    let Value::Array(entries) = reply else {
+        return vec![];
+    };
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let Value::Array(fields) = entry else {
+                return None;
+            };
+            let args = match fields.get(3)? {
+                Value::Array(args) => args.iter().filter_map(reply::as_string).collect(),
+                _ => vec![],
+            };
+            let field = |i: usize| fields.get(i).and_then(reply::as_string).unwrap_or_default();
+            Some(Entry {
+                id: reply::as_f64(fields.first()?)? as u64,
+                timestamp: reply::as_f64(fields.get(1)?)? as u64,
+                duration_usec: reply::as_f64(fields.get(2)?)? as u64,
+                args,
+                client_addr: field(4),
+                client_name: field(5),
+            })
+        })
+        .collect()
+}
+
+// Server the slow log was read from and ID of its newest entry.
+#[derive(Debug, PartialEq)]
+struct Seen {
+    run_id: String,
+    last_id: Option<u64>,
+}
+
+// Returns the entries added since the previous collection and remembers the
+// newest ID. The first collection only records a baseline. Slow log IDs start
+// over when Redis restarts, so every entry of a new `run_id` is new.
+fn new_entries(seen: &mut Option<Seen>, run_id: &str, entries: Vec<Entry>) -> Vec<Entry> {
+    let newest = entries.iter().map(|entry| entry.id).max();
+    let (new_entries, last_id) = match seen.take() {
+        None => (vec![], newest),
+        Some(previous) if previous.run_id != run_id => (entries, newest),
+        Some(Seen {
+            last_id: Some(last),
+            ..
+        }) => (
+            entries
+                .into_iter()
+                .filter(|entry| entry.id > last)
+                .collect(),
+            newest.max(Some(last)),
+        ),
+        Some(Seen { last_id: None, .. }) => (entries, newest),
+    };
+    *seen = Some(Seen {
+        run_id: run_id.to_string(),
+        last_id,
+    });
+    new_entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, duration_usec: u64, cmd: &str) -> Value {
+        Value::Array(vec![
+            Value::Int(id as i64),
+            Value::Int(1_700_000_000),
+            Value::Int(duration_usec as i64),
+            Value::Array(vec![
+                Value::BulkString(cmd.as_bytes().to_vec()),
+                Value::BulkString(b"key".to_vec()),
+            ]),
+            Value::BulkString(b"127.0.0.1:51234".to_vec()),
+            Value::BulkString(b"".to_vec()),
+        ])
+    }
+
+    #[test]
+    fn only_new_entries_are_counted() {
+        let ids = |entries: &[Entry]| entries.iter().map(|e| e.id).collect::<Vec<_>>();
+        let last_id = |seen: &Option<Seen>| seen.as_ref().and_then(|seen| seen.last_id);
+        let mut seen = None;
+
+        let first = parse_entries(&Value::Array(vec![
+            entry(4, 20_000, "KEYS"),
+            entry(3, 15_000, "GET"),
+        ]));
+        assert_eq!(first[0].command(), "keys");
+        assert!(new_entries(&mut seen, "a", first.clone()).is_empty());
+        assert_eq!(last_id(&seen), Some(4));
+
+        let second = parse_entries(&Value::Array(vec![
+            entry(6, 30_000, "SET"),
+            entry(5, 12_000, "GET"),
+            entry(4, 20_000, "KEYS"),
+        ]));
+        assert_eq!(ids(&new_entries(&mut seen, "a", second)), vec![6, 5]);
+        assert!(new_entries(&mut seen, "a", vec![]).is_empty());
+        assert_eq!(last_id(&seen), Some(6));
+
+        // Redis restarted and its IDs started over past the remembered one
+        let restarted: Vec<Value> = (0..10)
+            .rev()
+            .map(|id| entry(id, 11_000, "HGETALL"))
+            .collect();
+        let restarted = parse_entries(&Value::Array(restarted));
+        assert_eq!(
+            ids(&new_entries(&mut seen, "b", restarted)),
+            (0..10).rev().collect::<Vec<_>>()
+        );
+        assert_eq!(last_id(&seen), Some(9));
+
+        // Restarted again with an empty slow log
+        assert!(new_entries(&mut seen, "c", vec![]).is_empty());
+        assert_eq!(last_id(&seen), None);
+    }
+
+    #[test]
+    fn entries_after_an_empty_baseline_are_counted() {
+        let mut seen = None;
+        assert!(new_entries(&mut seen, "a", vec![]).is_empty());
+        assert_eq!(
+            seen,
+            Some(Seen {
+                run_id: "a".to_string(),
+                last_id: None
+            })
+        );
+
+        let entries = parse_entries(&Value::Array(vec![
+            entry(1, 25_000, "KEYS"),
+            entry(0, 12_000, "GET"),
+        ]));
+        let ids: Vec<u64> = new_entries(&mut seen, "a", entries)
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, vec![1, 0]);
+        assert_eq!(seen.and_then(|seen| seen.last_id), Some(1));
+    }
+}